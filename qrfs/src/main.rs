@@ -1,26 +1,133 @@
-#![allow(unused_must_use)]
 #![allow(dead_code)]
 
 use std::str;
+use std::fmt;
 use std::mem;
 use std::env;
-use std::ffi::OsStr;
-use fuse::{Filesystem, Request, ReplyCreate, ReplyEmpty, ReplyAttr, ReplyEntry, ReplyOpen, ReplyData, ReplyDirectory, ReplyWrite, FileType, FileAttr};
+use std::ffi::{OsStr, CString};
+use fuse::{Filesystem, Request, ReplyCreate, ReplyEmpty, ReplyAttr, ReplyEntry, ReplyOpen, ReplyData, ReplyDirectory, ReplyWrite, ReplyXattr, FileType, FileAttr};
 use time::get_time;
 use time::Timespec;
-use libc::{getuid, getgid, ENOSYS, ENOENT, EIO, EISDIR, ENOSPC};
+use libc::{c_int, getuid, getgid, ENOENT, EIO, EISDIR, ENOSPC, EFBIG, EINVAL, ENOTDIR, ENOTEMPTY, EEXIST, RENAME_NOREPLACE, RENAME_EXCHANGE, EPERM, EACCES, ENODATA, ERANGE, E2BIG, ENOTSUP, ENAMETOOLONG, XATTR_CREATE, XATTR_REPLACE};
+use libc::{sigset_t, timespec, sigemptyset, sigaddset, pthread_sigmask, sigtimedwait, isatty, umount2, SIG_BLOCK, SIGINT, SIGTERM, SIGHUP, STDIN_FILENO, MNT_DETACH};
+use libc::{R_OK, W_OK, X_OK, O_ACCMODE, O_RDONLY, O_WRONLY, O_RDWR, O_TRUNC, O_EXCL, S_ISVTX, S_ISGID};
 use serde::{Serialize, Deserialize};
 use bincode::{serialize, deserialize};
 use qrcode::QrCode;
 use image::Luma;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::os::unix::ffi::OsStrExt;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::{Duration, Instant};
+use std::thread;
+use std::ptr;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::cell::Cell;
+use std::io::Write;
 use native_dialog::FileDialog;
 extern crate ncurses;
 use ncurses::{getch, initscr, addstr, endwin, refresh, clear};
 
 const DEFAULT_SIZE:usize = 1024;
-const MAX_FILES_DIRECTORY:usize = 64;
 const NAMELEN: u32 = 64;
+const DIRECT_BLOCKS: usize = 12;
+const SINGLE_INDIRECT: usize = DIRECT_BLOCKS;
+const DOUBLE_INDIRECT: usize = DIRECT_BLOCKS + 1;
+const FILE_REFERENCES: usize = DIRECT_BLOCKS + 2;
+const INLINE_LINK_SIZE: usize = 60;
+const MAX_INODES: usize = 1 << 20;
+const STAT_BLOCK_SIZE: u64 = 512;
+const QR_CHUNK_SIZE: usize = 2048;
+const XATTR_NAME_MAX: usize = 255;
+const XATTR_SIZE_MAX: usize = 65536;
+const XATTR_LIST_MAX: usize = 65536;
+const XATTR_USER_PREFIX: &str = "user.";
+const DEFAULT_QR_PATH: &str = "qr_codes";
+const QR_PATH_VARIABLE: &str = "QRFS_QR_DIR";
+const LOG_LEVEL_VARIABLE: &str = "QRFS_LOG";
+const INODE_OBJECT_ID: u64 = 0;
+const BLOCK_OBJECT_ID: u64 = 1 << 32;
+const BITMAP_OBJECT_ID: u64 = 2 << 32;
+
+//Se activa cuando el filesystem se desmonta por una señal, en ese caso no se le pregunta nada al usuario
+static TERMINATING: AtomicBool = AtomicBool::new(false);
+
+//Registro de las operaciones, se configura en main con --log-level y --log-file
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/*
+    Errores de las operaciones del disco, los callbacks de fuse los traducen
+    a errno con la función errno
+*/
+#[derive(Debug, Clone, PartialEq)]
+enum QrfsError {
+    NotFound,
+    AlreadyExists,
+    NoSpace,
+    FileTooBig,
+    NameTooLong,
+    InvalidName,
+    InvalidArgument,
+    NotDirectory,
+    IsDirectory,
+    NotEmpty,
+    PermissionDenied,
+    NotPermitted,
+    NoAttribute,
+    NameOutOfRange,
+    BufferTooSmall,
+    ValueTooBig,
+    NotSupported,
+    Corrupted,
+    Storage(String)
+}
+
+impl QrfsError {
+    /*
+        Función que traduce el error al errno que se le responde al kernel
+        E: N/A
+        S: el errno del error
+    */
+    fn errno(&self) -> c_int {
+        match self {
+            QrfsError::NotFound => ENOENT,
+            QrfsError::AlreadyExists => EEXIST,
+            QrfsError::NoSpace => ENOSPC,
+            QrfsError::FileTooBig => EFBIG,
+            QrfsError::NameTooLong => ENAMETOOLONG,
+            QrfsError::InvalidName => EINVAL,
+            QrfsError::InvalidArgument => EINVAL,
+            QrfsError::NotDirectory => ENOTDIR,
+            QrfsError::IsDirectory => EISDIR,
+            QrfsError::NotEmpty => ENOTEMPTY,
+            QrfsError::PermissionDenied => EACCES,
+            QrfsError::NotPermitted => EPERM,
+            QrfsError::NoAttribute => ENODATA,
+            QrfsError::NameOutOfRange => ERANGE,
+            QrfsError::BufferTooSmall => ERANGE,
+            QrfsError::ValueTooBig => E2BIG,
+            QrfsError::NotSupported => ENOTSUP,
+            QrfsError::Corrupted => EIO,
+            QrfsError::Storage(_) => EIO
+        }
+    }
+}
+
+impl fmt::Display for QrfsError {
+    /*
+        Función que describe el error para los mensajes de consola
+    */
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QrfsError::Storage(message) => write!(formatter, "{}", message),
+            QrfsError::Corrupted => write!(formatter, "los datos del disco están dañados"),
+            error => write!(formatter, "{:?} (errno {})", error, error.errno())
+        }
+    }
+}
 
 /*
     Estructura fundamental que nos sirve para administrar los archivos
@@ -28,9 +135,13 @@ const NAMELEN: u32 = 64;
 struct Disk {
     super_block: Vec<Option<Inode>>,
     memory_blocks: Vec<MemoryBlock>,
+    inode_bitmap: Bitmap,
+    block_bitmap: Bitmap,
     max_files: usize,
     block_size: usize,
-    root_path: String
+    root_path: String,
+    dirty_inodes: BTreeSet<u64>,
+    dirty_blocks: BTreeSet<usize>
 }
 
 impl Disk {
@@ -42,7 +153,9 @@ impl Disk {
     fn new(root_path: String, memory_size: usize, block_size: usize) -> Disk{
         let memory_quantity: usize = (memory_size / block_size) - 1;
         let inode_size = mem::size_of::<Vec<Inode>>() + mem::size_of::<Inode>();
-        let max_files = block_size / inode_size;
+        //La tabla de inodes empieza con lo que cabe en un bloque y crece hasta MAX_INODES cuando se llena
+        let initial_files = block_size / inode_size;
+        let max_files = MAX_INODES;
 
         let mut memory_blocks: Vec<MemoryBlock>;
         let mut super_block: Vec<Option<Inode>>;
@@ -61,26 +174,25 @@ impl Disk {
             crtime: ttl,
             kind: FileType::Directory,
             perm: 0o755,
-            nlink: 0,
+            nlink: 2,
             uid: unsafe{getuid()},
             gid: unsafe{getgid()},
             rdev: 0,
             flags: 0
         };
 
-        let name:String = String::from(".");
-
         let root_inode = Inode {
-            name,
             attributes,
-            references: vec![Option::None; MAX_FILES_DIRECTORY]
+            references: vec![Option::None; FILE_REFERENCES],
+            link_target: None,
+            xattrs: BTreeMap::new()
         };
 
         super_block.push(Some(root_inode));
 
         //Inicializar los demás campos en NONE
 
-        for _ in super_block.len()..max_files {
+        for _ in super_block.len()..initial_files {
             super_block.push(Option::None);
         }
 
@@ -88,115 +200,388 @@ impl Disk {
             memory_blocks.push(MemoryBlock { data: Option::None });
         }
 
-        println!("Disco Inicializado Correctamente");
+        let mut inode_bitmap = Bitmap::new(initial_files);
+        let block_bitmap = Bitmap::new(memory_quantity);
+        inode_bitmap.set(0);
 
-        Disk { 
+        log(LogLevel::Info, "Disco Inicializado Correctamente");
+
+        let mut disk = Disk { 
             super_block, 
             memory_blocks, 
+            inode_bitmap,
+            block_bitmap,
             max_files, 
             block_size, 
-            root_path 
-        }
+            root_path,
+            dirty_inodes: BTreeSet::new(),
+            dirty_blocks: BTreeSet::new()
+        };
+
+        disk.init_directory(1, 1).expect("Error: no hay espacio para las entradas del root");
+        disk
 
     }
 
     /*
-        Función que encuentra el siguiente ino que esté vacío = None
+        Función que reserva el siguiente ino libre según el bitmap de inodes,
+        si la tabla de inodes está llena se duplica antes de reservar
         E: N/A
-        S: un opcional del índice al siguiente ino
+        S: el ino reservado o NoSpace si ya se llegó a MAX_INODES
     */
-    fn find_next_ino(&self) -> Option<u64>{
-        for i in 0..self.super_block.len() - 1 {
-            if let Option::None = self.super_block[i] {
-                let ino = (i as u64) + 1;
-                return Option::Some(ino);
-            }
+    fn allocate_ino(&mut self) -> Result<u64, QrfsError> {
+        if self.inode_bitmap.free == 0 && !self.grow_inodes(self.super_block.len() * 2) {
+            return Err(QrfsError::NoSpace);
         }
 
-        Option::None
+        self.inode_bitmap.allocate()
+            .map(|position| (position as u64) + 1)
+            .ok_or(QrfsError::NoSpace)
     }
 
     /*
-        Función que encuentra un memory block vacío
-        E: N/A
-        S: un opcional de índice al memory block vacío
+        Función que agranda la tabla de inodes y su bitmap, nunca pasa de max_files
+        E: size (cantidad de inodes deseada)
+        S: true si la tabla creció
     */
-    fn find_empty_memory_block(&self) -> Option<usize> {
-        for i in 0..self.memory_blocks.len() - 1 {
-            if let Option::None = self.memory_blocks[i].data {
-                return Option::Some(i);
-            }
+    fn grow_inodes(&mut self, size: usize) -> bool {
+        let size = size.min(self.max_files);
+        if size <= self.super_block.len() {
+            return false;
         }
 
-        Option::None
+        self.super_block.resize_with(size, || None);
+        self.inode_bitmap.grow(size);
+        true
+    }
+
+    /*
+        Función que crea las entradas "." y ".." de una carpeta nueva
+        E: ino (identificador de la carpeta) y parent (identificador de la carpeta padre)
+        S: un resultado con el errno si no se pudo reservar el bloque de entradas
+    */
+    fn init_directory(&mut self, ino: u64, parent: u64) -> Result<(), QrfsError> {
+        self.add_entry(ino, ".", ino)?;
+        self.add_entry(ino, "..", parent)
     }
 
     /*
-        Función que encuentra una referencia vacío dentro de un inode
+        Función que revisa que un inode sea una carpeta antes de tocar sus bloques como entradas
         E: ino (identificador del inode)
-        S: un opcional de índice al espacio vacío de la referencia
+        S: Ok, NotFound si no existe o NotDirectory si no es una carpeta
     */
-    fn find_empty_reference(&self, ino:u64) -> Option<usize> {
-        let position = (ino as usize) - 1;
+    fn check_directory(&self, ino: u64) -> Result<(), QrfsError> {
+        match self.get_inode(ino) {
+            Some(inode) if inode.attributes.kind == FileType::Directory => Ok(()),
+            Some(_) => Err(QrfsError::NotDirectory),
+            None => Err(QrfsError::NotFound)
+        }
+    }
 
-        match &self.super_block[position] {
-            Some(inode) => inode.references.iter().position(|i| i == &None),
-            None => panic!("Espacio de memoria inválido")
+    /*
+        Función que calcula cuántos bloques de entradas tiene una carpeta,
+        cada bloque es un bucket de la tabla hash de nombres
+        E: ino (identificador de la carpeta)
+        S: la cantidad de bloques
+    */
+    fn directory_blocks(&self, ino: u64) -> usize {
+        match self.get_inode(ino) {
+            Some(inode) if inode.attributes.kind == FileType::Directory => {
+                (inode.attributes.size as usize).div_ceil(self.block_size)
+            },
+            _ => 0
+        }
+    }
+
+    /*
+        Función que duplica los bloques de una carpeta y redistribuye sus entradas,
+        si algún bucket se sigue llenando se vuelve a duplicar antes de reservar los bloques
+        E: ino (identificador de la carpeta)
+        S: un resultado con NotDirectory si el inode no es una carpeta o el errno si no se pudieron reservar los bloques nuevos
+    */
+    fn grow_directory(&mut self, ino: u64) -> Result<(), QrfsError> {
+        self.check_directory(ino)?;
+
+        let per_block = self.entries_per_block();
+        let entries: Vec<DirectoryEntry> = self.get_entries(ino)?.into_iter().flatten().collect();
+        let current = self.directory_blocks(ino);
+        let mut count = (current * 2).max(1);
+
+        let buckets = loop {
+            let mut buckets: Vec<Vec<Option<DirectoryEntry>>> = vec![vec![Option::None; per_block]; count];
+
+            let placed = entries.iter().all(|entry| {
+                let bucket = &mut buckets[(hash_name(&entry.name) % count as u64) as usize];
+                match bucket.iter().position(|slot| slot.is_none()) {
+                    Some(slot) => {
+                        bucket[slot] = Some(entry.clone());
+                        true
+                    },
+                    None => false
+                }
+            });
+
+            if placed {
+                break buckets;
+            }
+            count *= 2;
+        };
+
+        for index in current..count {
+            if let Err(error) = self.get_or_allocate_file_block(ino, index) {
+                //La liberación es lo mejor que se puede hacer, el error original es el que se reporta
+                for allocated in current..=index {
+                    let _ = self.release_file_block(ino, allocated);
+                }
+                self.update_blocks(ino);
+                return Err(error);
+            }
+        }
+        self.update_blocks(ino);
+
+        for (index, bucket) in buckets.iter().enumerate() {
+            let block = self.get_file_block(ino, index)?.ok_or(QrfsError::Corrupted)?;
+            self.write_content_bytes(block, serialize(bucket).unwrap())?;
+        }
+
+        let size = (count * self.block_size) as u64;
+        let inode = self.get_inode_mutable(ino).ok_or(QrfsError::NotFound)?;
+        inode.attributes.size = size;
+        inode.attributes.mtime = get_time();
+        Ok(())
+    }
+
+    /*
+        Función que calcula cuántas entradas caben dentro de un bloque de una carpeta
+        cada entrada serializada ocupa a lo sumo 17 bytes más el nombre, más 8 bytes del largo del arreglo
+        E: N/A
+        S: la cantidad de entradas por bloque
+    */
+    fn entries_per_block(&self) -> usize {
+        (self.block_size - 8) / (17 + NAMELEN as usize)
+    }
+
+    /*
+        Función que lee las entradas guardadas en un bloque lógico de una carpeta
+        E: ino (identificador de la carpeta) e index (número de bloque lógico)
+        S: las entradas del bloque, vacías si el bloque no existe, o Corrupted si el bloque no se puede leer
+    */
+    fn read_directory_block(&self, ino: u64, index: usize) -> Result<Vec<Option<DirectoryEntry>>, QrfsError> {
+        let per_block = self.entries_per_block();
+
+        let content = match self.get_file_block(ino, index)? {
+            Some(block) => self.get_content_bytes(block).as_ref(),
+            None => None
+        };
+
+        match content {
+            Some(content) if !content.is_empty() => {
+                let entries: Vec<Option<DirectoryEntry>> = deserialize(&content[..]).map_err(|_| QrfsError::Corrupted)?;
+                if entries.len() != per_block {
+                    return Err(QrfsError::Corrupted);
+                }
+                Ok(entries)
+            },
+            _ => Ok(vec![None; per_block])
         }
     }
 
     /*
         Función que guarda un nuevo inode en el super block
         E: inode que se desea guardar
-        S: N/A
+        S: un resultado con el error si el ino no cabe en el super block
     */
-    fn write_inode(&mut self, inode: Inode) {
+    fn write_inode(&mut self, inode: Inode) -> Result<(), QrfsError> {
         if mem::size_of_val(&inode) > self.block_size {
-            println!("Error: Tamaño del Inode es incorrecto");
-            return ;
+            log(LogLevel::Error, "Tamaño del Inode es incorrecto");
+            return Err(QrfsError::InvalidArgument);
         }
 
-        let position = (inode.attributes.ino - 1) as usize;
+        let position = self.inode_position(inode.attributes.ino).ok_or(QrfsError::InvalidArgument)?;
+        self.inode_bitmap.set(position);
+        self.dirty_inodes.insert(inode.attributes.ino);
         self.super_block[position] = Some(inode);
+        Ok(())
     }
     
     /*
         Función que elimina un inode del superblock
+        libera el ino y los memory blocks del inode (datos o entradas) para que se puedan volver a usar
         E: ino (identificador del inode)
         S: N/A
     */
     fn remove_inode(&mut self, ino: u64) {
-        let position = (ino - 1) as usize;
+        let position = match self.inode_position(ino) {
+            Some(position) => position,
+            None => return
+        };
+
+        self.free_file_blocks(ino);
+
         self.super_block[position] = None;
+        self.inode_bitmap.clear(position);
+        self.dirty_inodes.insert(ino);
     }
 
     /*
-        Función que elimina una referencia dentro del inode
-        E: ino (identificador del inode) y reference (identificador del inode referenciado)
-        S: N/A
+        Función que elimina una entrada de una carpeta
+        E: ino (identificador del inode de la carpeta) y name (nombre de la entrada)
+        S: un resultado con NotFound si la entrada no existe
     */
-    fn remove_reference(&mut self, ino: u64, reference: usize) {
-        let position = (ino - 1) as usize;
-        let inode: &mut Option<Inode> = &mut self.super_block[position];
+    fn remove_entry(&mut self, ino: u64, name: &str) -> Result<(), QrfsError> {
+        match self.find_entry_position(ino, name)? {
+            Some((position, _)) => self.write_entry(ino, position, None),
+            None => Err(QrfsError::NotFound)
+        }
+    }
 
-        match inode {
-            Some(inode) => {
-                let mut reference_position: Option<usize> = Option::None;
+    /*
+        Función que cambia el inode al que apunta una entrada de una carpeta
+        E: ino (identificador del inode de la carpeta), name (nombre de la entrada) y value (ino nuevo)
+        S: un resultado con NotFound si la entrada no existe
+    */
+    fn set_entry_ino(&mut self, ino: u64, name: &str, value: u64) -> Result<(), QrfsError> {
+        match self.find_entry_position(ino, name)? {
+            Some((position, mut entry)) => {
+                entry.ino = value;
+                self.write_entry(ino, position, Some(entry))
+            },
+            None => Err(QrfsError::NotFound)
+        }
+    }
 
-                for i in 0..inode.references.len() {
-                    if inode.references[i].unwrap() == reference {
-                        reference_position = Some(i);
-                        break;
-                    }
-                }
+    /*
+        Función que agrega una entrada a una carpeta en el bucket que le corresponde a su nombre,
+        si el bucket está lleno la carpeta se duplica y se vuelve a intentar
+        E: ino (identificador del inode de la carpeta), name (nombre de la entrada) y value (ino referenciado)
+        S: un resultado con ENOTDIR si el inode no es una carpeta, ENAMETOOLONG si el nombre es muy largo,
+           EEXIST si el nombre ya existe o ENOSPC si no hay bloques libres
+    */
+    fn add_entry(&mut self, ino: u64, name: &str, value: u64) -> Result<(), QrfsError> {
+        self.check_directory(ino)?;
+
+        if name.len() > NAMELEN as usize {
+            return Err(QrfsError::NameTooLong);
+        }
 
-                match reference_position {
-                    Some(refer) => inode.references[refer] = None,
-                    None => panic!("Error: referencia no encontrada")
+        if self.find_entry_position(ino, name)?.is_some() {
+            return Err(QrfsError::AlreadyExists);
+        }
+
+        let per_block = self.entries_per_block();
+
+        loop {
+            let blocks = self.directory_blocks(ino);
+
+            if blocks > 0 {
+                let index = (hash_name(name) % blocks as u64) as usize;
+                let bucket = self.read_directory_block(ino, index)?;
+
+                if let Some(slot) = bucket.iter().position(|entry| entry.is_none()) {
+                    let entry = DirectoryEntry { name: name.to_owned(), ino: value };
+                    return self.write_entry(ino, index * per_block + slot, Some(entry));
                 }
-            },
-            None => panic!("Error: Inode no existe en el contexto actual")
+            }
+
+            self.grow_directory(ino)?;
+        }
+    }
+
+    /*
+        Función que aumenta la cantidad de links de un inode
+        E: ino (identificador del inode)
+        S: N/A
+    */
+    fn increase_nlink(&mut self, ino: u64) {
+        if let Some(inode) = self.get_inode_mutable(ino) {
+            inode.attributes.nlink += 1;
+            inode.attributes.ctime = get_time();
+        }
+    }
+
+    /*
+        Función que disminuye la cantidad de links de un inode
+        E: ino (identificador del inode)
+        S: N/A
+    */
+    fn decrease_nlink(&mut self, ino: u64) {
+        if let Some(inode) = self.get_inode_mutable(ino) {
+            inode.attributes.nlink = inode.attributes.nlink.saturating_sub(1);
+            inode.attributes.ctime = get_time();
+        }
+    }
+
+    /*
+        Función que encuentra la carpeta que contiene a una carpeta por medio de su entrada ".."
+        E: ino (identificador del inode de la carpeta)
+        S: un opcional del ino de la carpeta padre, None para el root
+    */
+    fn find_parent(&self, ino: u64) -> Result<Option<u64>, QrfsError> {
+        if ino == 1 {
+            return Ok(None);
+        }
+
+        self.find_entry(ino, "..")
+    }
+
+    /*
+        Función que actualiza los links cuando una carpeta cambia de carpeta padre
+        cambia su entrada ".." y la cantidad de links de ambas carpetas padre
+        E: ino (identificador del inode movido), parent (padre anterior) y newparent (padre nuevo)
+        S: un resultado con el error si no se pudo cambiar la entrada ".."
+    */
+    fn move_directory(&mut self, ino: u64, parent: u64, newparent: u64) -> Result<(), QrfsError> {
+        let is_directory = self.get_inode(ino).is_some_and(|inode| inode.attributes.kind == FileType::Directory);
+
+        if is_directory && parent != newparent {
+            self.set_entry_ino(ino, "..", newparent)?;
+            self.decrease_nlink(parent);
+            self.increase_nlink(newparent);
+        }
+
+        Ok(())
+    }
+
+    /*
+        Función que indica si una carpeta no tiene más entradas que "." y ".."
+        E: ino (identificador del inode de la carpeta)
+        S: true si la carpeta está vacía o Corrupted si no se pueden leer sus entradas
+    */
+    fn is_empty_directory(&self, ino: u64) -> Result<bool, QrfsError> {
+        Ok(self.get_entries(ino)?.iter()
+            .flatten()
+            .all(|entry| entry.name == "." || entry.name == ".."))
+    }
+
+    /*
+        Función que indica si un inode está dentro del árbol de una carpeta
+        E: ancestor (ino de la carpeta) e ino (identificador del inode)
+        S: true si ino es ancestor o está dentro de ancestor
+    */
+    fn is_descendant(&self, ancestor: u64, ino: u64) -> Result<bool, QrfsError> {
+        let mut current = Some(ino);
+
+        while let Some(ino) = current {
+            if ino == ancestor {
+                return Ok(true);
+            }
+            current = self.find_parent(ino)?;
+        }
+
+        Ok(false)
+    }
+
+    /*
+        Función que calcula la posición de un inode dentro del super block
+        E: ino (identificador del inode)
+        S: un opcional de la posición, None si el ino no existe en el disco
+    */
+    fn inode_position(&self, ino: u64) -> Option<usize> {
+        if ino == 0 || ino as usize > self.super_block.len() {
+            return None;
         }
+
+        Some(ino as usize - 1)
     }
 
     /*
@@ -205,7 +590,8 @@ impl Disk {
         S: un opcional del inode solicitado mutable
     */
     fn get_inode_mutable(&mut self, ino: u64) -> Option<&mut Inode> {
-        let position = (ino as usize) - 1;
+        let position = self.inode_position(ino)?;
+        self.dirty_inodes.insert(ino);
 
         match &mut self.super_block[position] {
             Some(inode) => Some(inode),
@@ -219,7 +605,7 @@ impl Disk {
         S: un opcional del inode solicitado
     */
     fn get_inode(&self, ino: u64) -> Option<&Inode> {
-        let position = (ino as usize) - 1;
+        let position = self.inode_position(ino)?;
 
         match &self.super_block[position] {
             Some(inode) => Some(inode),
@@ -228,53 +614,60 @@ impl Disk {
     }
 
     /*
-        Función que encuentra un inode por el nombre
+        Función que encuentra el ino de una entrada de una carpeta por el nombre
         E: parent_ino (identificador del inode padre) y name (nombre del archivo o carpeta)
-        S: un opcional del inode solicitado
+        S: un opcional del ino de la entrada o Corrupted si el bucket no se puede leer
     */
-    fn find_inode_name(&self, parent_ino: u64, name: &str) -> Option<&Inode> {
-        let position = (parent_ino as usize) - 1;
-        let parent_inode = &self.super_block[position];
-
-        match parent_inode {
-            Some(parent_inode) => {
-                for referenced_inode in parent_inode.references.iter(){
-                    if let Some(ino) = referenced_inode {
-                        let position = (ino.clone() as usize) - 1;
-                        let reference = &self.super_block[position];
-
-                        match reference {
-                            Some(inode) => {
-                                let name_inode:&str = inode.name.as_str();
-                                let requested_name = name.trim();
-                                
-                                if name_inode == requested_name {
-                                    return Some(inode);
-                                }
-                            },
-                            None => panic!("Error: No se encontró el inode")
-                        }
-                    }
-                }
-            },
-            None => panic!("Error: No se encontró el inode parent")
+    fn find_entry(&self, parent_ino: u64, name: &str) -> Result<Option<u64>, QrfsError> {
+        Ok(self.find_entry_position(parent_ino, name)?.map(|(_, entry)| entry.ino))
+    }
+
+    /*
+        Función que encuentra la posición de una entrada de una carpeta por el nombre,
+        solo se lee el bloque (bucket) que corresponde al hash del nombre
+        E: parent_ino (identificador del inode padre) y name (nombre de la entrada)
+        S: un opcional con la posición y la entrada o Corrupted si el bucket no se puede leer
+    */
+    fn find_entry_position(&self, parent_ino: u64, name: &str) -> Result<Option<(usize, DirectoryEntry)>, QrfsError> {
+        let blocks = self.directory_blocks(parent_ino);
+        if blocks == 0 {
+            return Ok(None);
         }
 
-        return None;
+        let index = (hash_name(name) % blocks as u64) as usize;
+        let per_block = self.entries_per_block();
+
+        Ok(self.read_directory_block(parent_ino, index)?.into_iter()
+            .enumerate()
+            .find_map(|(slot, entry)| match entry {
+                Some(entry) if entry.name == name => Some((index * per_block + slot, entry)),
+                _ => None
+            }))
+    }
+
+    /*
+        Función que encuentra un inode por el nombre
+        E: parent_ino (identificador del inode padre) y name (nombre del archivo o carpeta)
+        S: un opcional del inode solicitado o Corrupted si la carpeta no se puede leer
+    */
+    fn find_inode_name(&self, parent_ino: u64, name: &str) -> Result<Option<&Inode>, QrfsError> {
+        Ok(self.find_entry(parent_ino, name)?.and_then(|ino| self.get_inode(ino)))
     }
 
     /*
-        Función que regresa las referencias de un inode
+        Función que regresa las entradas de una carpeta leyendo todos sus bloques,
+        la posición de cada entrada cambia cuando la carpeta crece
         E: ino (identificador del inode)
-        S: un opcional de las referencias del inode
+        S: las entradas de la carpeta o Corrupted si algún bloque no se puede leer
     */
-    fn get_references(&self, ino: u64) -> &Vec<Option<usize>> {
-        let position = (ino as usize) - 1;
+    fn get_entries(&self, ino: u64) -> Result<Vec<Option<DirectoryEntry>>, QrfsError> {
+        let mut entries: Vec<Option<DirectoryEntry>> = Vec::new();
 
-        match &self.super_block[position] {
-            Some(inode) => &inode.references,
-            None => panic!("Error: No se encontró el inode para referencias")
+        for index in 0..self.directory_blocks(ino) {
+            entries.extend(self.read_directory_block(ino, index)?);
         }
+
+        Ok(entries)
     }
 
     /*
@@ -283,754 +676,4201 @@ impl Disk {
         S: un opcional del contenido del memory block como bytes
     */
     fn get_content_bytes(&self, block_position: usize) -> &Option<Vec<u8>> {
-        let memory_block = &self.memory_blocks[block_position];
-        return &memory_block.data;
+        match self.memory_blocks.get(block_position) {
+            Some(memory_block) => &memory_block.data,
+            None => &None
+        }
     }
 
     /*
         Función que guarda el contenido en un memory block
         E: block_position (la ubicación del bloque solicitado) y el contenido (bytes)
-        S: N/A
+        S: un resultado con el error si el bloque no existe o el contenido no cabe
     */
-    fn write_content_bytes(&mut self, block_position: usize, content: Vec<u8>) {
+    fn write_content_bytes(&mut self, block_position: usize, content: Vec<u8>) -> Result<(), QrfsError> {
         if content.len() > self.block_size {
-            panic!("Error: el contenido es muy grande");
+            return Err(QrfsError::FileTooBig);
+        }
+
+        if block_position >= self.memory_blocks.len() {
+            return Err(QrfsError::Corrupted);
         }
 
         let memory_block = MemoryBlock{ data: Some(content)};
         self.memory_blocks[block_position] = memory_block;
+        self.block_bitmap.set(block_position);
+        self.dirty_blocks.insert(block_position);
+        Ok(())
     }
 
     /*
-        Función que guardan una referencia en un inode
-        E: ino (identificador del inode), reference (ubicación de la referencia) y value (ino de la referencia)
+        Función que libera un memory block
+        E: block_position (la ubicación del bloque a liberar)
         S: N/A
     */
-    fn write_reference(&mut self, ino: u64, reference: usize, value: usize) {
-        let position = (ino as usize) - 1;
-        match &mut self.super_block[position] {
-            Some(inode) => {
-                inode.references[reference] = Some(value)
-            },
-            None => panic!("Error: no se encontro el inode para insertar la referencia")
+    fn free_block(&mut self, block_position: usize) {
+        if block_position >= self.memory_blocks.len() {
+            return ;
         }
+
+        self.memory_blocks[block_position] = MemoryBlock { data: None };
+        self.block_bitmap.clear(block_position);
+        self.dirty_blocks.insert(block_position);
     }
 
     /*
-        Función que codifica los memory_blocks
-        E: N/A
-        S: un arreglo de bytes que representa los memory blocks codificados
+        Función que libera todos los memory blocks de un archivo, incluidos los indirectos
+        E: ino (identificador del inode)
+        S: N/A
     */
-    fn encode_memory_blocks(&self,) -> Vec<u8> {
-        serialize(&self.memory_blocks).unwrap()
+    fn free_file_blocks(&mut self, ino: u64) {
+        let references: Vec<Option<usize>> = match self.get_inode_mutable(ino) {
+            Some(inode) => {
+                let references = inode.references.clone();
+                inode.references = vec![None; FILE_REFERENCES];
+                inode.attributes.blocks = 0;
+                references
+            },
+            None => return
+        };
+
+        for (position, reference) in references.iter().enumerate() {
+            if let Some(block) = *reference {
+                match position {
+                    SINGLE_INDIRECT => self.free_indirect_block(block, 1),
+                    DOUBLE_INDIRECT => self.free_indirect_block(block, 2),
+                    _ => self.free_block(block)
+                }
+            }
+        }
     }
 
     /*
-        Función que codifica un inode
-        E: inode (inode a codificar)
-        S: un arreglo de bytes (un inode codificado)
+        Función que libera un bloque indirecto y los bloques a los que apunta
+        E: block (posición del bloque indirecto) y level (niveles de indirección)
+        S: N/A
     */
-    fn encode_inode(&self, inode: &Inode) -> Vec<u8> {
-        serialize(inode).unwrap()
+    fn free_indirect_block(&mut self, block: usize, level: usize) {
+        //Si el bloque está corrupto no se conocen sus punteros y solo se libera el bloque mismo
+        let pointers = self.read_pointers(block).unwrap_or_default();
+        for pointer in pointers.into_iter().flatten() {
+            if level > 1 {
+                self.free_indirect_block(pointer, level - 1);
+            } else {
+                self.free_block(pointer);
+            }
+        }
+
+        self.free_block(block);
     }
 
     /*
-        Función que codifica todos los inodes del disco
-        E: N/A
-        S: un arreglo de arreglos de bytes (todos los inodes codificado)
+        Función que obtiene todos los memory blocks de un archivo, incluidos los indirectos
+        E: ino (identificador del inode)
+        S: un arreglo con las posiciones de los bloques
     */
-    fn encode_inodes(&self) -> Vec<Vec<u8>> {
-        let mut result: Vec<Vec<u8>> = Vec::new();
-        
-        for inode in self.super_block.iter() {
-            match inode {
-                Some(inode) => {
-                    if inode.attributes.ino != 1 {
-                        result.push(self.encode_inode(inode));
+    fn file_blocks(&self, ino: u64) -> Vec<usize> {
+        let mut blocks: Vec<usize> = Vec::new();
+
+        if let Some(inode) = self.get_inode(ino) {
+            for (position, reference) in inode.references.iter().enumerate() {
+                if let Some(block) = *reference {
+                    match position {
+                        SINGLE_INDIRECT => self.indirect_blocks(block, 1, &mut blocks),
+                        DOUBLE_INDIRECT => self.indirect_blocks(block, 2, &mut blocks),
+                        _ => blocks.push(block)
                     }
-                },
-                None => ()
+                }
             }
         }
 
-        result
+        blocks
     }
 
     /*
-        Función que decodifica los memory_blocks
-        E: un arreglo de bytes que representan los memory blocks codificado
-        S: un arreglo de MemoryBlock
+        Función que recalcula el campo blocks de un inode a partir de los memory blocks reservados,
+        incluidos los indirectos, en unidades de 512 bytes como espera st_blocks
+        E: ino (identificador del inode)
+        S: N/A
     */
-    fn decode_memory_blocks(&self, memory_blocks: &Vec<u8>) -> Vec<MemoryBlock>{
-        deserialize(&memory_blocks[..]).unwrap()
-    }
+    fn update_blocks(&mut self, ino: u64) {
+        let count = self.file_blocks(ino).len() as u64;
+        let blocks = (count * self.block_size as u64).div_ceil(STAT_BLOCK_SIZE);
 
-    /*
-        Función que decodifica un inode
-        E: inode (arreglo de bytes => inode codificado)
-        S: un inode (un inode decodificado)
-    */
-    fn decode_inode(&self, inode: &Vec<u8>) -> Inode {
-        deserialize(&inode[..]).unwrap()
+        if let Some(inode) = self.get_inode_mutable(ino) {
+            inode.attributes.blocks = blocks;
+        }
     }
 
     /*
-        Función que decodifica todos los inodes
-        E: todos los inodes(un arreglo de arreglos de bytes => inodes codificados)
-        S: un arreglo de inodes decodificados
+        Función que agrega un bloque indirecto y los bloques a los que apunta
+        E: block (posición del bloque indirecto), level (niveles de indirección) y blocks (arreglo destino)
+        S: N/A
     */
-    fn decode_inodes(&self, inodes: Vec<Vec<u8>>) -> Vec<Inode> {
-        let mut result: Vec<Inode> = Vec::new();
-        
-        for inode in inodes.iter() {
-            result.push(self.decode_inode(inode));
+    fn indirect_blocks(&self, block: usize, level: usize, blocks: &mut Vec<usize>) {
+        let pointers = self.read_pointers(block).unwrap_or_default();
+        for pointer in pointers.into_iter().flatten() {
+            if level > 1 {
+                self.indirect_blocks(pointer, level - 1, blocks);
+            } else {
+                blocks.push(pointer);
+            }
         }
 
-        result
+        blocks.push(block);
     }
 
     /*
-        Función que guarda los inodes y llama a la función para convertirlos en QR
+        Función que calcula cuántos punteros caben dentro de un bloque indirecto
+        cada puntero serializado ocupa a lo sumo 9 bytes más 8 bytes del largo del arreglo
         E: N/A
-        S: N/A
+        S: la cantidad de punteros por bloque
     */
-    fn save_inodes(&self) {
-        self.transform_inodes_to_qr(self.encode_inodes());
+    fn pointers_per_block(&self) -> usize {
+        (self.block_size - 8) / 9
     }
 
     /*
-        Función que transforma los inodes codificados en QR
-        E: un arreglo de bytes que representa los inodes codificados
-        S: N/A
+        Función que calcula el tamaño máximo de un archivo con los bloques directos y los indirectos simple y doble
+        E: N/A
+        S: el tamaño máximo en bytes
     */
-    fn transform_inodes_to_qr(&self, inodes: Vec<Vec<u8>>) {
-        let mut counter = 0;
-        for inode in inodes.iter() {
-            let qr_code = QrCode::new(inode).unwrap();
-            let image_qr = qr_code.render::<Luma<u8>>().build();
-            let path = format!("/home/kzumbado/Documents/proyecto2-sistemas-operativos/qrfs/qr_codes/inode{}.png", counter);
-            image_qr.save(path);
-            counter = counter + 1;
-        }
+    fn max_file_size(&self) -> u64 {
+        let pointers = self.pointers_per_block();
+        ((DIRECT_BLOCKS + pointers + pointers * pointers) * self.block_size) as u64
     }
 
     /*
-        Función que convierte los QR a inodes
-        E: un arreglo con los paths a los archivos seleccionados por el usuario
-        S: un arreglo de opcionales de inodes (super_block)
+        Función que obtiene los bloques lógicos de un archivo que tienen un memory block reservado
+        E: ino (identificador del inode) y first (primer bloque lógico que interesa)
+        S: los bloques lógicos en orden desde first, o Corrupted si un bloque indirecto no se puede leer
     */
-    fn translate_inodes_qr(&self, paths: Vec<PathBuf>) -> Vec<Option<Inode>> {
-        let mut super_block:Vec<Option<Inode>> = Vec::new();
-        for inode in paths.iter() {
-            let image_qr = image::open(inode).unwrap();
-            let gray_image = image_qr.into_luma8();
-
-            let mut decoder = quircs::Quirc::default();
+    fn allocated_indexes(&self, ino: u64, first: usize) -> Result<Vec<usize>, QrfsError> {
+        let inode = match self.get_inode(ino) {
+            Some(inode) => inode,
+            None => return Ok(Vec::new())
+        };
 
-            let with_image = gray_image.width() as usize;
-            let height_image = gray_image.height() as usize;
-            let data = decoder.identify(with_image, height_image, &gray_image);
+        let pointers = self.pointers_per_block();
+        let mut indexes: Vec<usize> = Vec::new();
 
-            let mut result: Option<Vec<u8>> = Option::None;
-            for element in data {
-                let code = element.expect("Error: no se pudo identificar el QR");
-                let decoded = code.decode().expect("Error: no se pudo decodicar el QR");
-                result = Some(decoded.payload);
+        for (index, reference) in inode.references.iter().take(DIRECT_BLOCKS).enumerate() {
+            if reference.is_some() {
+                indexes.push(index);
             }
+        }
 
-            match result {
-                Some(result) => {
-                    let inode: Inode = self.decode_inode(&result);
-                    super_block.push(Some(inode));
-                },
-                None => panic!("Error: no se pudo transformar a Inode")
+        if let Some(Some(block)) = inode.references.get(SINGLE_INDIRECT) {
+            for (index, pointer) in self.read_pointers(*block)?.iter().enumerate() {
+                if pointer.is_some() {
+                    indexes.push(DIRECT_BLOCKS + index);
+                }
             }
+        }
 
+        if let Some(Some(block)) = inode.references.get(DOUBLE_INDIRECT) {
+            for (outer, indirect) in self.read_pointers(*block)?.iter().enumerate() {
+                if let Some(indirect) = indirect {
+                    for (inner, pointer) in self.read_pointers(*indirect)?.iter().enumerate() {
+                        if pointer.is_some() {
+                            indexes.push(DIRECT_BLOCKS + pointers + outer * pointers + inner);
+                        }
+                    }
+                }
+            }
         }
 
-        super_block
+        indexes.retain(|index| *index >= first);
+        Ok(indexes)
     }
 
     /*
-        Función que despliega un dialogo de selección de archivos al usuario
+        Función que reserva un memory block vacío
         E: N/A
-        S: un arreglo con los paths a los archivos seleccionados por el usuario
+        S: la posición del bloque reservado o ENOSPC si el disco está lleno
     */
-    fn display_dialog(&self) -> Vec<PathBuf> {
-        let paths = FileDialog::new()
-            .set_location("~/Documents/proyecto2-sistemas-operativos/qrfs/qr_codes")
-            .add_filter("PNG Image", &["png"])
-            .show_open_multiple_file()
-            .unwrap();
+    fn allocate_block(&mut self) -> Result<usize, QrfsError> {
+        match self.block_bitmap.allocate() {
+            Some(block) => {
+                self.write_content_bytes(block, Vec::new())?;
+                Ok(block)
+            },
+            None => Err(QrfsError::NoSpace)
+        }
+    }
 
-        println!("El path es {:?}", paths);
-        paths
+    /*
+        Función que lee los punteros guardados en un bloque indirecto
+        E: block (posición del bloque indirecto)
+        S: el arreglo de punteros del bloque, siempre con pointers_per_block posiciones,
+           o Corrupted si el bloque no se puede leer
+    */
+    fn read_pointers(&self, block: usize) -> Result<Vec<Option<usize>>, QrfsError> {
+        let per_block = self.pointers_per_block();
+
+        match self.get_content_bytes(block) {
+            Some(content) if !content.is_empty() => {
+                let pointers: Vec<Option<usize>> = deserialize(&content[..]).map_err(|_| QrfsError::Corrupted)?;
+                if pointers.len() != per_block {
+                    return Err(QrfsError::Corrupted);
+                }
+                Ok(pointers)
+            },
+            _ => Ok(vec![None; per_block])
+        }
     }
 
-}
+    /*
+        Función que guarda los punteros de un bloque indirecto
+        E: block (posición del bloque indirecto) y pointers (arreglo de punteros)
+        S: un resultado con el error si no se pudo escribir el bloque
+    */
+    fn write_pointers(&mut self, block: usize, pointers: Vec<Option<usize>>) -> Result<(), QrfsError> {
+        self.write_content_bytes(block, serialize(&pointers).unwrap())
+    }
 
-/*
-    Estructura de inode para guardar los datos de los archivos y carpetas
-    implementa serialize y deserialize
-*/
-#[derive(Serialize, Deserialize)]
-struct Inode {
-    name: String,
-    #[serde(with = "FileAttrDef")]
-    attributes: FileAttr,
-    references: Vec<Option<usize>>
-}
-
-/*
-    Estructura de inode para guardar los datos de los archivos y carpetas
-    implementa serialize y deserialize
-*/
-#[derive(Serialize, Deserialize)]
-struct MemoryBlock {
-    data: Option<Vec<u8>>
-}
+    /*
+        Función que encuentra el memory block que guarda un bloque lógico de un archivo
+        los primeros DIRECT_BLOCKS son directos, luego se usa el indirecto simple y después el doble
+        E: ino (identificador del inode) e index (número de bloque lógico dentro del archivo)
+        S: un opcional de la posición del memory block, None si el bloque no está reservado,
+           o Corrupted si un bloque indirecto no se puede leer
+    */
+    fn get_file_block(&self, ino: u64, index: usize) -> Result<Option<usize>, QrfsError> {
+        let inode = match self.get_inode(ino) {
+            Some(inode) => inode,
+            None => return Ok(None)
+        };
+        let reference = |position: usize| inode.references.get(position).cloned().flatten();
+        let pointers = self.pointers_per_block();
 
-/*
-    Estructura auxiliar de Timespec
-    es necesaria ya que serialize y deserialize no puede tener estructuras dentro de estructuras
-    implementa serialize y deserialize
-*/
-#[derive(Serialize, Deserialize)]
-#[serde(remote = "Timespec")]
-pub struct TimespecDef {
-    pub sec: i64,
-    pub nsec: i32,
-}
+        if index < DIRECT_BLOCKS {
+            return Ok(reference(index));
+        }
 
-/*
-    Estructura auxiliar de FileType
-    es necesaria ya que serialize y deserialize no puede tener estructuras dentro de estructuras
-    implementa serialize y deserialize
-*/
-#[derive(Serialize, Deserialize)]
-#[serde(remote = "FileType")]
-pub enum FileTypeDef {
-    NamedPipe,
-    CharDevice,
-    BlockDevice,
-    Directory,
-    RegularFile,
-    Symlink,
-    Socket,
-}
+        let index = index - DIRECT_BLOCKS;
+        if index < pointers {
+            return match reference(SINGLE_INDIRECT) {
+                Some(indirect) => Ok(self.read_pointers(indirect)?[index]),
+                None => Ok(None)
+            };
+        }
 
-/*
-    Estructura auxiliar de FileAttr
-    es necesaria ya que serialize y deserialize no puede tener estructuras dentro de estructuras
-    implementa serialize y deserialize
-*/
-#[derive(Serialize, Deserialize)]
-#[serde(remote = "FileAttr")]
-pub struct FileAttrDef {
-    pub ino: u64,
-    pub size: u64,
-    pub blocks: u64,
-    #[serde(with = "TimespecDef")]
-    pub atime: Timespec,
-    #[serde(with = "TimespecDef")]
-    pub mtime: Timespec,
-    #[serde(with = "TimespecDef")]
-    pub ctime: Timespec,
-    #[serde(with = "TimespecDef")]
-    pub crtime: Timespec,
-    #[serde(with = "FileTypeDef")]
-    pub kind: FileType,
-    pub perm: u16,
-    pub nlink: u32,
-    pub uid: u32,
-    pub gid: u32,
-    pub rdev: u32,
-    pub flags: u32,
-}
+        let index = index - pointers;
+        if index < pointers * pointers {
+            let indirect = match reference(DOUBLE_INDIRECT) {
+                Some(double_indirect) => self.read_pointers(double_indirect)?[index / pointers],
+                None => None
+            };
+
+            return match indirect {
+                Some(indirect) => Ok(self.read_pointers(indirect)?[index % pointers]),
+                None => Ok(None)
+            };
+        }
 
-/*
-    Estructura del filesystem
-*/
-struct QRFS {
-    disk: Disk
-}
+        Ok(None)
+    }
 
-impl QRFS {
     /*
-        Función que crea un nuevo QRFS
-        E: el path raíz
-        S: una estructura QRFS
+        Función que encuentra o reserva el memory block de un bloque lógico de un archivo
+        reserva también los bloques indirectos que hagan falta
+        E: ino (identificador del inode) e index (número de bloque lógico dentro del archivo)
+        S: la posición del memory block o el errno en caso de fallar
     */
-    fn new(root_path: String) -> Self {
-        let max_files: usize = DEFAULT_SIZE;
-        let memory_size: usize = DEFAULT_SIZE * DEFAULT_SIZE * DEFAULT_SIZE;
-        let block_size: usize = max_files * (mem::size_of::<Vec<Inode>>() + mem::size_of::<Inode>());
-        
-        initscr();
-        addstr("¿Desea seleccionar archivos previos? \nY = sí\nCualquiera = no\n");
-        
-        refresh();
-        
-        let response = getch();
-        clear();
-        endwin();
-        let mut disk = Disk::new(root_path, memory_size, block_size);
-
-        if response == 89 {
-            let paths = disk.display_dialog();
-            let inodes = disk.translate_inodes_qr(paths);
-
-            for inode in inodes {
-                match inode {
-                    Some(inode) => {
-                        let position = disk.find_empty_reference(1);
-                        match position {
-                            Some(pos) => {
-                                let ino = inode.attributes.ino as usize;
-                                let content: Vec<u8> = Vec::default();
-
-                                disk.write_inode(inode);
-                                disk.write_reference(1, pos, ino);
-                                disk.write_content_bytes(ino - 1, content);
-                            },
-                            None => println!("Error: fallo al inicializar el fs")
-                        }
-                    },
-                    None => panic!("Error: el inode no es válido")
-                }
-            }
-
-            println!("Exito");
+    fn get_or_allocate_file_block(&mut self, ino: u64, index: usize) -> Result<usize, QrfsError> {
+        if let Some(block) = self.get_file_block(ino, index)? {
+            return Ok(block);
         }
 
-        QRFS { disk }
-    }
-}
+        let pointers = self.pointers_per_block();
 
-impl Drop for QRFS {
-    /*
-        Función drop del filesystem
-        
-        sirve para guardar los archivos en caso de que el usuario desee hacerlo
-    */
-    fn drop(&mut self) {
+        if index < DIRECT_BLOCKS {
+            return self.get_or_allocate_reference(ino, index, false);
+        }
 
-        initscr();
-        addstr("¿Desea guardar los archivos? \nY = sí\nCualquiera = no\n");
-        
-        refresh();
-        
-        let response = getch();
-        
-        endwin();
-        
-        if response == 89 {
-            println!("\nGuardando inodes");
-            &self.disk.save_inodes();
-            println!("Guardados correctamente\n");
+        let index = index - DIRECT_BLOCKS;
+        if index < pointers {
+            let indirect = self.get_or_allocate_reference(ino, SINGLE_INDIRECT, true)?;
+            return self.get_or_allocate_pointer(indirect, index, false);
+        }
+
+        let index = index - pointers;
+        if index < pointers * pointers {
+            let double_indirect = self.get_or_allocate_reference(ino, DOUBLE_INDIRECT, true)?;
+            let indirect = self.get_or_allocate_pointer(double_indirect, index / pointers, true)?;
+            return self.get_or_allocate_pointer(indirect, index % pointers, false);
         }
+
+        Err(QrfsError::FileTooBig)
     }
-}
 
-impl Filesystem for QRFS {
     /*
-        Función lookup del filesystem
-        
-        sirve para detectar los archivos o carpetas pertenecientes del fs
+        Función que encuentra o reserva el bloque de una referencia del inode
+        E: ino (identificador del inode), reference (posición de la referencia) e indirect (si el bloque guarda punteros)
+        S: la posición del memory block o el errno en caso de fallar
     */
-    fn lookup(&mut self, _req: &Request, parent: u64, name: &std::ffi::OsStr, reply: ReplyEntry) {
-        println!("Operation: lookup");
+    fn get_or_allocate_reference(&mut self, ino: u64, reference: usize, indirect: bool) -> Result<usize, QrfsError> {
+        let inode = self.get_inode_mutable(ino).ok_or(QrfsError::NotFound)?;
 
-        let inode = self.disk.find_inode_name(parent, name.to_str().unwrap());
+        if inode.references.len() < FILE_REFERENCES {
+            inode.references.resize(FILE_REFERENCES, None);
+        }
 
-        match inode {
-            Some(inode) => {
-                let ttl = get_time();
-                reply.entry(&ttl, &inode.attributes, 0);
-            },
-            None => reply.error(ENOENT)
+        if let Some(block) = inode.references[reference] {
+            return Ok(block);
+        }
+
+        let block = self.allocate_block()?;
+        if indirect {
+            self.write_pointers(block, vec![None; self.pointers_per_block()])?;
         }
+
+        self.write_reference(ino, reference, block)?;
+        Ok(block)
     }
 
     /*
-        Función getattr del filesystem
-        
-        sirve para obtener los atributos de un archivo o carpeta
+        Función que encuentra o reserva el bloque al que apunta un puntero de un bloque indirecto
+        E: block (posición del bloque indirecto), index (posición del puntero) e indirect (si el bloque nuevo guarda punteros)
+        S: la posición del memory block o el errno en caso de fallar
     */
-    fn getattr(&mut self, _req: &Request, ino: u64, reply: ReplyAttr) {
-        println!("Operation: getattr");
+    fn get_or_allocate_pointer(&mut self, block: usize, index: usize, indirect: bool) -> Result<usize, QrfsError> {
+        let mut pointers = self.read_pointers(block)?;
 
-        match self.disk.get_inode(ino) {
-            Some(inode) => {
-                let ttl = get_time();
-                reply.attr(&ttl, &inode.attributes);
-            },
-            None => reply.error(ENOENT)
+        if let Some(pointer) = pointers[index] {
+            return Ok(pointer);
+        }
+
+        let pointer = self.allocate_block()?;
+        if indirect {
+            self.write_pointers(pointer, vec![None; self.pointers_per_block()])?;
         }
+
+        pointers[index] = Some(pointer);
+        self.write_pointers(block, pointers)?;
+        Ok(pointer)
     }
 
     /*
-        Función read del filesystem
-        
-        sirve para leer el contenido de un archivo del fs
+        Función que libera el memory block de un bloque lógico de un archivo
+        también libera los bloques indirectos que quedan vacíos
+        E: ino (identificador del inode) e index (número de bloque lógico dentro del archivo)
+        S: un resultado con Corrupted si un bloque indirecto no se puede leer
     */
-    fn read(&mut self, _req: &Request, ino: u64, _fh: u64, _offset: i64, _size: u32, reply: ReplyData) {
-        println!("Operation: read");
+    fn release_file_block(&mut self, ino: u64, index: usize) -> Result<(), QrfsError> {
+        let pointers = self.pointers_per_block();
+
+        let (reference, path): (usize, Vec<usize>) = if index < DIRECT_BLOCKS {
+            (index, vec![])
+        } else if index - DIRECT_BLOCKS < pointers {
+            (SINGLE_INDIRECT, vec![index - DIRECT_BLOCKS])
+        } else if index - DIRECT_BLOCKS - pointers < pointers * pointers {
+            let index = index - DIRECT_BLOCKS - pointers;
+            (DOUBLE_INDIRECT, vec![index / pointers, index % pointers])
+        } else {
+            return Ok(());
+        };
 
-        let memory_block = self.disk.get_content_bytes((ino - 1)  as usize);
-    
-        match memory_block {
-            Some(memory_block) => {
-                reply.data(memory_block)
-            },
-            None => reply.error(EIO)
+        let block = match self.get_inode(ino).and_then(|inode| inode.references.get(reference).cloned().flatten()) {
+            Some(block) => block,
+            None => return Ok(())
+        };
+
+        if self.release_pointer(block, &path)? {
+            if let Some(inode) = self.get_inode_mutable(ino) {
+                if let Some(slot) = inode.references.get_mut(reference) {
+                    *slot = None;
+                }
+            }
         }
+
+        Ok(())
     }
 
     /*
-        Función readdir del filesystem
-        
-        sirve para detectar los directorios del fs
+        Función que recorre los bloques indirectos y libera el bloque final del camino
+        E: block (bloque actual) y path (posiciones de los punteros a seguir)
+        S: true si el bloque actual quedó libre y se debe quitar su puntero, Corrupted si un bloque
+           indirecto no se puede leer (en ese caso no se modifica)
     */
-    fn readdir(&mut self, _req: &Request, ino: u64, _fh: u64, offset: i64, mut reply: ReplyDirectory) {
-        println!("Operation: readdir");
+    fn release_pointer(&mut self, block: usize, path: &[usize]) -> Result<bool, QrfsError> {
+        if path.is_empty() {
+            self.free_block(block);
+            return Ok(true);
+        }
 
-        if ino == 1 {
-            if offset == 0 {
-                reply.add(1, 0, FileType::Directory, ".");
-                reply.add(1, 1, FileType::Directory, "..");
+        let mut pointers = self.read_pointers(block)?;
+        if let Some(pointer) = pointers[path[0]] {
+            if self.release_pointer(pointer, &path[1..])? {
+                pointers[path[0]] = None;
             }
         }
 
-        let inode: Option<&Inode> = self.disk.get_inode(ino);
-
-        if mem::size_of_val(&inode) == offset as usize {
-            reply.ok();
-            return;
+        if pointers.iter().all(|pointer| pointer.is_none()) {
+            self.free_block(block);
+            Ok(true)
+        } else {
+            self.write_pointers(block, pointers)?;
+            Ok(false)
         }
+    }
 
-        match inode {
-            Some(inode) => {
-                let references = &inode.references;
+    /*
+        Función que cambia el tamaño de un archivo
+        al reducirlo libera los bloques sobrantes y al aumentarlo el espacio nuevo se lee como ceros
+        E: ino (identificador del inode) y size (tamaño nuevo)
+        S: un resultado con el errno en caso de fallar, FileTooBig si el tamaño no cabe en los punteros del inode
+    */
+    fn truncate_file(&mut self, ino: u64, size: u64) -> Result<(), QrfsError> {
+        let current = self.get_inode(ino).ok_or(QrfsError::NotFound)?.attributes.size;
+        let block_size = self.block_size as u64;
 
-                for ino in references.iter() {
-                    if let Some(ino) = ino {
-                        let inode = self.disk.get_inode(*ino as u64);
+        if size > self.max_file_size() {
+            return Err(QrfsError::FileTooBig);
+        }
 
-                        if let Some(data) = inode {
-                            if data.attributes.ino == 1 {
-                                continue;
-                            }
+        if size < current {
+            let first = size.div_ceil(block_size) as usize;
+            let indexes = self.allocated_indexes(ino, first)?;
 
-                            let name = data.name.clone();
-                            let offset = mem::size_of_val(&inode) as i64;
-                            reply.add(data.attributes.ino, offset, data.attributes.kind, name);
-                        }
+            //Solo se recorren los bloques reservados, un archivo disperso puede tener un tamaño enorme
+            for index in indexes.into_iter().rev() {
+                if let Err(error) = self.release_file_block(ino, index) {
+                    self.update_blocks(ino);
+                    return Err(error);
+                }
+            }
+            self.update_blocks(ino);
+
+            let remainder = (size % block_size) as usize;
+            if remainder != 0 {
+                if let Some(block) = self.get_file_block(ino, (size / block_size) as usize)? {
+                    if let Some(mut content) = self.get_content_bytes(block).clone() {
+                        content.truncate(remainder);
+                        self.write_content_bytes(block, content)?;
                     }
                 }
-                reply.ok();
-            },
-            None => reply.error(ENOENT)
+            }
         }
+
+        let inode = self.get_inode_mutable(ino).ok_or(QrfsError::NotFound)?;
+        let ttl = get_time();
+        inode.attributes.size = size;
+        inode.attributes.mtime = ttl;
+        inode.attributes.ctime = ttl;
+
+        Ok(())
     }
 
     /*
-        Función mkdir del filesystem
-        
-        sirve para crear una nueva carpeta en el fs
+        Función que lee una parte del contenido de un archivo
+        recorre los bloques lógicos del archivo, los bloques sin reservar se leen como ceros
+        E: ino (identificador del inode), offset (posición inicial) y size (cantidad máxima de bytes)
+        S: los bytes leídos, vacío si el offset está después del final del archivo,
+           o Corrupted si un bloque indirecto no se puede leer
     */
-    fn mkdir(&mut self, _req: &Request, parent: u64, name: &OsStr, _mode: u32, reply: ReplyEntry) {
-        println!("Operation: mkdir");
-
-        let reference_position = self.disk.find_empty_reference(parent);
-
-        match reference_position {
-            Some(position) => {
-                let ino = self.disk.find_next_ino();
-
-                match ino {
-                    Some(ino) => {
-                        let ttl = get_time();
-                        let attributes = FileAttr {
-                            ino: ino as u64,
-                            size: 0,
-                            blocks: 1,
-                            atime: ttl,
-                            mtime: ttl,
-                            ctime: ttl,
-                            crtime: ttl,
-                            kind: FileType::Directory,
-                            perm: 0o755,
-                            nlink: 0,
-                            uid: unsafe {getuid()},
-                            gid: unsafe {getgid()},
-                            rdev: 0,
-                            flags: 0
-                        };
-
-                        let name:String = name.to_str().unwrap().to_owned();
-
-                        let inode = Inode {
-                            name,
-                            attributes,
-                            references: vec![Option::None; MAX_FILES_DIRECTORY]
-                        };
-
-                        self.disk.write_inode(inode);
-                        self.disk.write_reference(parent, position, ino as usize);
-
-                        reply.entry(&ttl, &attributes, 0);
+    fn read_file(&self, ino: u64, offset: usize, size: usize) -> Result<Vec<u8>, QrfsError> {
+        let file_size = match self.get_inode(ino) {
+            Some(inode) => inode.attributes.size as usize,
+            None => return Ok(Vec::new())
+        };
 
-                    },
-                    None => reply.error(ENOSPC)
-                }
-            },
-            None => println!("Disco lleno")
+        if offset >= file_size {
+            return Ok(Vec::new());
         }
-    }
 
-    /*
-        Función create del filesystem
-        
-        sirve para crear un nuevo archivo en el fs
-    */
-    fn create(&mut self, _req: &Request, parent: u64, name: &OsStr, _mode: u32, flags: u32, reply: ReplyCreate) {
-        println!("Operation: create");
+        let end = (offset + size).min(file_size);
+        let mut result: Vec<u8> = Vec::with_capacity(end - offset);
+        let mut position = offset;
 
-        let reference_position = self.disk.find_empty_reference(parent);
+        while position < end {
+            let index = position / self.block_size;
+            let start = position % self.block_size;
+            let length = (self.block_size - start).min(end - position);
 
-        if reference_position == None {
-            println!("Error: No es posible crear más archivos");
-            reply.error(EIO);
-            return ;
+            let content: &[u8] = match self.get_file_block(ino, index)? {
+                Some(block) => match self.get_content_bytes(block) {
+                    Some(content) => content,
+                    None => &[]
+                },
+                None => &[]
+            };
+
+            let from = start.min(content.len());
+            let to = (start + length).min(content.len());
+            result.extend_from_slice(&content[from..to]);
+            result.resize(result.len() + length - (to - from), 0);
+
+            position += length;
         }
 
-        let next_ino = self.disk.find_next_ino();
-        let memory_block = self.disk.find_empty_memory_block();
+        Ok(result)
+    }
 
-        if next_ino == None || memory_block == None {
-            println!("Error: No queda espacio disponible");
-            reply.error(ENOSPC);
-            return ;
+    /*
+        Función que escribe dentro de un archivo a partir de un offset
+        reserva los bloques necesarios y actualiza el tamaño del archivo,
+        si el disco se llena a la mitad se regresa la cantidad escrita hasta ese momento
+        E: ino (identificador del inode), offset (posición inicial) y data (bytes a escribir)
+        S: la cantidad de bytes escritos o el errno en caso de fallar
+    */
+    fn write_file(&mut self, ino: u64, offset: usize, data: &[u8]) -> Result<usize, QrfsError> {
+        if self.get_inode(ino).is_none() {
+            return Err(QrfsError::NotFound);
         }
 
-        let next_ino = next_ino.unwrap();
-        let memory_block = memory_block.unwrap();
+        let mut written: usize = 0;
 
-        let ttl = get_time();
+        while written < data.len() {
+            let position = offset + written;
+            let index = position / self.block_size;
+            let start = position % self.block_size;
+            let length = (self.block_size - start).min(data.len() - written);
 
-        let attributes = FileAttr {
-            ino: next_ino,
-            size: 0,
-            blocks: 1,
-            atime: ttl,
-            mtime: ttl,
-            ctime: ttl,
-            crtime: ttl,
-            kind: FileType::RegularFile,
-            perm: 0o755,
-            nlink: 0,
-            uid: unsafe {getuid()},
-            gid: unsafe {getgid()},
-            rdev: 0,
-            flags
-        };
+            //Si falla a la mitad se pudieron reservar bloques indirectos, blocks se recalcula antes de salir
+            let block = match self.get_or_allocate_file_block(ino, index) {
+                Ok(block) => block,
+                Err(error) if written == 0 => {
+                    self.update_blocks(ino);
+                    return Err(error);
+                },
+                Err(_) => break
+            };
 
-        let name = name.to_str().unwrap().to_owned();
+            let mut content: Vec<u8> = match self.get_content_bytes(block) {
+                Some(content) => content.clone(),
+                None => Vec::new()
+            };
 
-        let mut inode = Inode {
-            name,
-            attributes,
-            references: vec![Option::None; MAX_FILES_DIRECTORY]
-        };
+            if content.len() < start + length {
+                content.resize(start + length, 0);
+            }
+            content[start..start + length].copy_from_slice(&data[written..written + length]);
+            match self.write_content_bytes(block, content) {
+                Err(error) if written == 0 => {
+                    self.update_blocks(ino);
+                    return Err(error);
+                },
+                Err(_) => break,
+                Ok(()) => ()
+            }
+
+            written += length;
+        }
 
-        inode.references[0] = Some(memory_block);
-        let content: Vec<u8> = Vec::default();
+        self.update_blocks(ino);
+        let inode = self.get_inode_mutable(ino).ok_or(QrfsError::NotFound)?;
+        let ttl = get_time();
+        inode.attributes.size = inode.attributes.size.max((offset + written) as u64);
+        inode.attributes.mtime = ttl;
+        inode.attributes.ctime = ttl;
 
-        self.disk.write_inode(inode);
-        self.disk.write_content_bytes(memory_block, content);
+        Ok(written)
+    }
 
-        let reference_position = reference_position.unwrap();
-        self.disk.write_reference(parent, reference_position, next_ino as usize);
-        
-        reply.created(&ttl, &attributes, 1, next_ino, flags);
+    /*
+        Función que guarda el destino de un link simbólico, los destinos cortos se guardan
+        dentro del inode y los largos en memory blocks como el contenido de un archivo
+        E: ino (identificador del link) y target (destino del link)
+        S: un resultado con el errno si no se pudo escribir el destino completo
+    */
+    fn write_link_target(&mut self, ino: u64, target: &[u8]) -> Result<(), QrfsError> {
+        if target.len() <= INLINE_LINK_SIZE {
+            let inode = self.get_inode_mutable(ino).ok_or(QrfsError::NotFound)?;
+            inode.link_target = Some(target.to_vec());
+            inode.attributes.size = target.len() as u64;
+            return Ok(());
+        }
+
+        if self.write_file(ino, 0, target)? < target.len() {
+            return Err(QrfsError::NoSpace);
+        }
+
+        Ok(())
     }
 
     /*
-        Función open del filesystem
-        
-        sirve para abrir un archivo del fs
+        Función que lee el destino de un link simbólico
+        E: ino (identificador del link)
+        S: el destino del link, NotFound si no existe o InvalidArgument si no es un link simbólico
     */
-    fn open(&mut self, _req: &Request, ino: u64, flags: u32, reply: ReplyOpen) {
-        println!("Operation: open");
+    fn read_link_target(&self, ino: u64) -> Result<Vec<u8>, QrfsError> {
+        let inode = self.get_inode(ino).ok_or(QrfsError::NotFound)?;
 
-        let inode = self.disk.get_inode(ino);
+        if inode.attributes.kind != FileType::Symlink {
+            return Err(QrfsError::InvalidArgument);
+        }
 
-        match inode {
-            Some(_) => reply.opened(ino, flags),
-            None => reply.error(ENOSYS)
+        match &inode.link_target {
+            Some(target) => Ok(target.clone()),
+            None => self.read_file(ino, 0, inode.attributes.size as usize)
         }
     }
-    
+
     /*
-        Función write del filesystem
-        
-        sirve para escribir dentro de un archivo del fs
+        Función que guarda un atributo extendido en un inode
+        E: ino (identificador del inode), name (nombre del atributo), value (valor) y
+           flags (XATTR_CREATE o XATTR_REPLACE)
+        S: Ok o el código de error si el atributo no cumple los límites o las flags
     */
-    fn write(&mut self, _req: &Request, ino: u64, _fh: u64, _offset: i64, data: &[u8], _flags: u32, reply: ReplyWrite) {
-        println!("Operation: write");
+    fn set_xattr(&mut self, ino: u64, name: &str, value: &[u8], flags: c_int) -> Result<(), QrfsError> {
+        if name.len() > XATTR_NAME_MAX {
+            return Err(QrfsError::NameOutOfRange);
+        }
+        if value.len() > XATTR_SIZE_MAX {
+            return Err(QrfsError::ValueTooBig);
+        }
 
-        let inode = self.disk.get_inode_mutable(ino);
-        let content: Vec<u8> = data.to_vec();
+        let inode = match self.get_inode_mutable(ino) {
+            Some(inode) => inode,
+            None => return Err(QrfsError::NotFound)
+        };
 
-        match inode {
-            Some(inode) => {
-                inode.attributes.size = data.len() as u64;
+        let exists = inode.xattrs.contains_key(name);
+        if flags & XATTR_CREATE != 0 && exists {
+            return Err(QrfsError::AlreadyExists);
+        }
+        if flags & XATTR_REPLACE != 0 && !exists {
+            return Err(QrfsError::NoAttribute);
+        }
 
-                let position = (ino as usize) - 1;
+        let list_size: usize = inode.xattrs.keys().filter(|key| key.as_str() != name).map(|key| key.len() + 1).sum();
+        if list_size + name.len() + 1 > XATTR_LIST_MAX {
+            return Err(QrfsError::NoSpace);
+        }
 
-                self.disk.write_content_bytes(position, content);
-                reply.written(data.len() as u32);
-            },
-            None => {
-                println!("Error: No se encontró el inode");
-                reply.error(ENOENT);
-            }
+        inode.xattrs.insert(name.to_owned(), value.to_vec());
+        inode.attributes.ctime = get_time();
+        Ok(())
+    }
+
+    /*
+        Función que obtiene el valor de un atributo extendido de un inode
+        E: ino (identificador del inode) y name (nombre del atributo)
+        S: el valor del atributo o ENODATA si no existe
+    */
+    fn get_xattr(&self, ino: u64, name: &str) -> Result<&Vec<u8>, QrfsError> {
+        match self.get_inode(ino) {
+            Some(inode) => inode.xattrs.get(name).ok_or(QrfsError::NoAttribute),
+            None => Err(QrfsError::NotFound)
         }
     }
 
     /*
-        Función rmdir del filesystem
-        
-        sirve para eliminar una carpeta del fs
+        Función que arma la lista de nombres de atributos extendidos de un inode,
+        cada nombre termina en un byte nulo como lo espera listxattr
+        E: ino (identificador del inode)
+        S: los nombres concatenados
     */
-    fn rmdir(&mut self, _req: &Request, parent: u64, name: &OsStr, reply: ReplyEmpty) {
-        let name = name.to_str().unwrap();
-        let inode = self.disk.find_inode_name(parent, name);
-        
-        match inode {
+    fn list_xattrs(&self, ino: u64) -> Result<Vec<u8>, QrfsError> {
+        match self.get_inode(ino) {
             Some(inode) => {
-                let ino = inode.attributes.ino;
-                self.disk.remove_reference(parent, ino as usize);
-                self.disk.remove_inode(ino);
-
-                reply.ok();
+                let mut names: Vec<u8> = Vec::new();
+                for name in inode.xattrs.keys() {
+                    names.extend_from_slice(name.as_bytes());
+                    names.push(0);
+                }
+                Ok(names)
             },
-            None => reply.error(EIO)
+            None => Err(QrfsError::NotFound)
         }
     }
 
     /*
-        Función statfs del filesystem
-        
-        sirve para desplegar las estadísticas del fs
+        Función que elimina un atributo extendido de un inode
+        E: ino (identificador del inode) y name (nombre del atributo)
+        S: Ok o ENODATA si no existe
     */
-    fn statfs(&mut self, _req: &Request, _ino: u64, reply: fuse::ReplyStatfs) {
-        println!("Operation: statfs");
+    fn remove_xattr(&mut self, ino: u64, name: &str) -> Result<(), QrfsError> {
+        match self.get_inode_mutable(ino) {
+            Some(inode) => {
+                if inode.xattrs.remove(name).is_none() {
+                    return Err(QrfsError::NoAttribute);
+                }
+                inode.attributes.ctime = get_time();
+                Ok(())
+            },
+            None => Err(QrfsError::NotFound)
+        }
+    }
 
-        let blocks = self.disk.memory_blocks.len();
-        let bfree = blocks - (self.disk.find_empty_memory_block().unwrap());
-        let bavail = bfree;
-        let bsize = self.disk.block_size;
-        let files = self.disk.find_empty_memory_block().unwrap();
-        let namelen = NAMELEN;
-        let ffree = self.disk.max_files - files;
-        let frsize = blocks as u32;
+    /*
+        Función que guarda o borra una entrada en una carpeta,
+        reserva el bloque de la entrada si todavía no existe y actualiza el tamaño de la carpeta
+        E: ino (identificador del inode), position (ubicación de la entrada) y entry (entrada a guardar, None para borrarla)
+        S: un resultado con el errno si no se pudo reservar el bloque
+    */
+    fn write_entry(&mut self, ino: u64, position: usize, entry: Option<DirectoryEntry>) -> Result<(), QrfsError> {
+        let per_block = self.entries_per_block();
+        let index = position / per_block;
+
+        let mut entries = self.read_directory_block(ino, index)?;
+        entries[position % per_block] = entry;
+
+        let block = self.get_or_allocate_file_block(ino, index)?;
+        self.update_blocks(ino);
+        self.write_content_bytes(block, serialize(&entries).unwrap())?;
+
+        let size = ((index + 1) * self.block_size) as u64;
+        let inode = self.get_inode_mutable(ino).ok_or(QrfsError::NotFound)?;
+        inode.attributes.size = inode.attributes.size.max(size);
+        inode.attributes.mtime = get_time();
+        Ok(())
+    }
 
-        reply.statfs(
-            blocks as u64, 
-            bfree as u64, 
-            bavail as u64, 
-            files as u64, 
-            ffree as u64, 
-            bsize as u32, 
-            namelen, 
-            frsize);
+    /*
+        Función que guardan una referencia en un inode
+        E: ino (identificador del inode), reference (ubicación de la referencia) y value (ino de la referencia)
+        S: un resultado con el error si el inode o la referencia no existen
+    */
+    fn write_reference(&mut self, ino: u64, reference: usize, value: usize) -> Result<(), QrfsError> {
+        let inode = self.get_inode_mutable(ino).ok_or(QrfsError::NotFound)?;
+        let slot = inode.references.get_mut(reference).ok_or(QrfsError::Corrupted)?;
+        *slot = Some(value);
+        Ok(())
     }
 
     /*
-        Función rename del filesystem
+        Función que codifica un memory block junto a su posición
+        E: position (ubicación del bloque) y memory_block (bloque a codificar)
+        S: un arreglo de bytes (un memory block codificado)
+    */
+    fn encode_memory_block(&self, position: usize, memory_block: &MemoryBlock) -> Vec<u8> {
+        serialize(&QrObject::Block(position, memory_block.clone())).unwrap()
+    }
+
+    /*
+        Función que codifica los memory_blocks que tienen contenido
+        E: N/A
+        S: un arreglo de tuplas (posición del bloque, bloque codificado)
+    */
+    fn encode_memory_blocks(&self) -> Vec<(usize, Vec<u8>)> {
+        let mut result: Vec<(usize, Vec<u8>)> = Vec::new();
+
+        for (position, memory_block) in self.memory_blocks.iter().enumerate() {
+            if memory_block.data.is_some() {
+                result.push((position, self.encode_memory_block(position, memory_block)));
+            }
+        }
+
+        result
+    }
+
+    /*
+        Función que codifica un inode
+        E: inode (inode a codificar)
+        S: un arreglo de bytes (un inode codificado)
+    */
+    fn encode_inode(&self, inode: &Inode) -> Vec<u8> {
+        serialize(&QrObject::Inode(inode.clone())).unwrap()
+    }
+
+    /*
+        Función que codifica todos los inodes del disco
+        E: N/A
+        S: un arreglo de tuplas (ino, inode codificado)
+    */
+    fn encode_inodes(&self) -> Vec<(usize, Vec<u8>)> {
+        let mut result: Vec<(usize, Vec<u8>)> = Vec::new();
         
-        sirve para renombrar o editar un archivo del fs
+        for inode in self.super_block.iter().flatten() {
+            let ino = inode.attributes.ino as usize;
+            result.push((ino, self.encode_inode(inode)));
+        }
+
+        result
+    }
+
+    /*
+        Función que codifica los bitmaps de inodes y memory blocks
+        E: N/A
+        S: un arreglo con una tupla (identificador, bitmaps codificados)
     */
-    fn rename(&mut self, _req: &Request, parent: u64, name: &OsStr, _newparent: u64, newname: &OsStr, reply: ReplyEmpty) {
-        println!("Operation: rename");
+    fn encode_bitmaps(&self) -> Vec<(usize, Vec<u8>)> {
+        let bitmaps = QrObject::Bitmaps(self.inode_bitmap.clone(), self.block_bitmap.clone());
+        vec![(0, serialize(&bitmaps).unwrap())]
+    }
 
-        let name = name.to_str().unwrap();
-        let inode = self.disk.find_inode_name(parent, name);
+    /*
+        Función que decodifica un objeto guardado en un QR
+        E: object (arreglo de bytes => inode o memory block codificado)
+        S: el objeto decodificado o Corrupted si los bytes no son válidos
+    */
+    fn decode_object(&self, object: &[u8]) -> Result<QrObject, QrfsError> {
+        deserialize(object).map_err(|_| QrfsError::Corrupted)
+    }
 
-        match inode {
-            Some(inode) => {
-                let ino = inode.attributes.ino;
-                let new_inode = self.disk.get_inode_mutable(ino);
-                
-                match new_inode {
-                    Some(inode) => {
-                        let newname = newname.to_str().unwrap().to_owned();
-                        inode.name = newname;
-                        reply.ok();
-                    },
-                    None => panic!("Error: No se pudo renombrar el archivo")
+    /*
+        Función que guarda todo el disco (inodes y memory blocks) como QR
+        crea la carpeta destino en caso de que no exista
+        E: directory (carpeta donde se guardan las imágenes)
+        S: un resultado con el mensaje de error en caso de fallar
+    */
+    fn save(&self, directory: &Path) -> Result<(), QrfsError> {
+        fs::create_dir_all(directory)
+            .map_err(|error| QrfsError::Storage(format!("no se pudo crear la carpeta {:?}: {}", directory, error)))?;
+
+        log(LogLevel::Info, "Guardando inodes");
+        self.save_inodes(directory)?;
+        log(LogLevel::Info, "Guardando memory blocks");
+        self.save_memory_blocks(directory)?;
+        log(LogLevel::Info, "Guardando bitmaps");
+        self.save_bitmaps(directory)?;
+        log(LogLevel::Info, &format!("Guardados correctamente en {:?}", directory));
+
+        Ok(())
+    }
+
+    /*
+        Función que guarda los inodes y llama a la función para convertirlos en QR
+        E: directory (carpeta donde se guardan las imágenes)
+        S: un resultado con el mensaje de error en caso de fallar
+    */
+    fn save_inodes(&self, directory: &Path) -> Result<(), QrfsError> {
+        self.transform_objects_to_qr(directory, "inode", INODE_OBJECT_ID, self.encode_inodes())
+    }
+
+    /*
+        Función que guarda los memory blocks y llama a la función para convertirlos en QR
+        E: directory (carpeta donde se guardan las imágenes)
+        S: un resultado con el mensaje de error en caso de fallar
+    */
+    fn save_memory_blocks(&self, directory: &Path) -> Result<(), QrfsError> {
+        self.transform_objects_to_qr(directory, "block", BLOCK_OBJECT_ID, self.encode_memory_blocks())
+    }
+
+    /*
+        Función que guarda los bitmaps y llama a la función para convertirlos en QR
+        E: directory (carpeta donde se guardan las imágenes)
+        S: un resultado con el mensaje de error en caso de fallar
+    */
+    fn save_bitmaps(&self, directory: &Path) -> Result<(), QrfsError> {
+        self.transform_objects_to_qr(directory, "bitmap", BITMAP_OBJECT_ID, self.encode_bitmaps())
+    }
+
+    /*
+        Función que guarda como QR todos los inodes y memory blocks modificados desde el último guardado
+        E: directory (carpeta donde se guardan las imágenes)
+        S: un resultado con el mensaje de error en caso de fallar
+    */
+    fn save_changes(&mut self, directory: &Path) -> Result<(), QrfsError> {
+        if !self.has_changes() {
+            return Ok(());
+        }
+
+        let inodes: Vec<u64> = self.dirty_inodes.iter().cloned().collect();
+        let blocks: Vec<usize> = self.dirty_blocks.iter().cloned().collect();
+        log(LogLevel::Info, &format!("Guardando {} inodes y {} memory blocks modificados", inodes.len(), blocks.len()));
+        self.save_objects(directory, &inodes, &blocks)?;
+
+        self.clear_changes();
+        Ok(())
+    }
+
+    /*
+        Función que guarda como QR los inodes y memory blocks indicados junto a los bitmaps,
+        los objetos liberados no se guardan porque los bitmaps ya los marcan como libres
+        E: directory (carpeta donde se guardan las imágenes), inodes (inos a guardar) y blocks (bloques a guardar)
+        S: un resultado con el mensaje de error en caso de fallar
+    */
+    fn save_objects(&self, directory: &Path, inodes: &[u64], blocks: &[usize]) -> Result<(), QrfsError> {
+        fs::create_dir_all(directory)
+            .map_err(|error| QrfsError::Storage(format!("no se pudo crear la carpeta {:?}: {}", directory, error)))?;
+
+        let inodes: Vec<(usize, Vec<u8>)> = inodes.iter()
+            .filter_map(|ino| self.get_inode(*ino).map(|inode| (*ino as usize, self.encode_inode(inode))))
+            .collect();
+        self.transform_objects_to_qr(directory, "inode", INODE_OBJECT_ID, inodes)?;
+
+        let blocks: Vec<(usize, Vec<u8>)> = blocks.iter()
+            .filter(|position| self.memory_blocks[**position].data.is_some())
+            .map(|position| (*position, self.encode_memory_block(*position, &self.memory_blocks[*position])))
+            .collect();
+        self.transform_objects_to_qr(directory, "block", BLOCK_OBJECT_ID, blocks)?;
+
+        self.save_bitmaps(directory)
+    }
+
+    /*
+        Función que revisa si hay inodes o memory blocks modificados sin guardar
+        E: N/A
+        S: true si hay cambios pendientes
+    */
+    fn has_changes(&self) -> bool {
+        !self.dirty_inodes.is_empty() || !self.dirty_blocks.is_empty()
+    }
+
+    /*
+        Función que marca todo el disco como guardado
+        E: N/A
+        S: N/A
+    */
+    fn clear_changes(&mut self) {
+        self.dirty_inodes.clear();
+        self.dirty_blocks.clear();
+    }
+
+    /*
+        Función que elimina las imágenes sobrantes de un objeto que ahora ocupa menos fragmentos
+        E: directory (carpeta de las imágenes), prefix (prefijo del nombre), id (identificador) y count (fragmentos actuales)
+        S: N/A
+    */
+    fn remove_stale_chunks(&self, directory: &Path, prefix: &str, id: usize, count: u32) {
+        let mut index = count;
+        loop {
+            let path = directory.join(format!("{}{}_{}.png", prefix, id, index));
+            if fs::remove_file(&path).is_err() {
+                break;
+            }
+            index += 1;
+        }
+    }
+
+    /*
+        Función que divide un objeto codificado en fragmentos que caben dentro de un QR
+        E: object_id (identificador del objeto) y object (objeto codificado)
+        S: un arreglo de fragmentos numerados
+    */
+    fn split_object(&self, object_id: u64, object: &[u8]) -> Vec<QrChunk> {
+        let checksum = checksum(object);
+        let count = object.len().div_ceil(QR_CHUNK_SIZE).max(1) as u32;
+        let mut chunks: Vec<QrChunk> = Vec::new();
+
+        for index in 0..count {
+            let start = (index as usize) * QR_CHUNK_SIZE;
+            let end = (start + QR_CHUNK_SIZE).min(object.len());
+
+            chunks.push(QrChunk {
+                object_id,
+                index,
+                count,
+                checksum,
+                data: object[start..end].to_vec()
+            });
+        }
+
+        chunks
+    }
+
+    /*
+        Función que une los fragmentos de un objeto sin importar el orden en que llegaron
+        E: object_id (identificador del objeto) y los fragmentos del objeto
+        S: un opcional del objeto codificado, None si falta algún fragmento o está corrupto
+    */
+    fn join_chunks(&self, object_id: u64, mut chunks: Vec<QrChunk>) -> Option<Vec<u8>> {
+        chunks.sort_by_key(|chunk| chunk.index);
+        chunks.dedup_by_key(|chunk| chunk.index);
+
+        let count = chunks[0].count;
+        let checksum_object = chunks[0].checksum;
+
+        if chunks.len() != count as usize {
+            log(LogLevel::Error, &format!("faltan fragmentos del objeto {} ({} de {})", object_id, chunks.len(), count));
+            return None;
+        }
+
+        let mut object: Vec<u8> = Vec::new();
+        for (position, chunk) in chunks.iter().enumerate() {
+            if chunk.index as usize != position || chunk.count != count || chunk.checksum != checksum_object {
+                log(LogLevel::Error, &format!("los fragmentos del objeto {} no coinciden", object_id));
+                return None;
+            }
+
+            object.extend_from_slice(&chunk.data);
+        }
+
+        if checksum(&object) != checksum_object {
+            log(LogLevel::Error, &format!("el checksum del objeto {} es incorrecto", object_id));
+            return None;
+        }
+
+        Some(object)
+    }
+
+    /*
+        Función que transforma los objetos codificados en QR, un QR por cada fragmento
+        E: directory (carpeta destino), prefix (prefijo del nombre de la imagen),
+           base_id (identificador base del tipo de objeto) y un arreglo de tuplas (identificador, objeto codificado)
+        S: un resultado con el mensaje de error en caso de fallar
+    */
+    fn transform_objects_to_qr(&self, directory: &Path, prefix: &str, base_id: u64, objects: Vec<(usize, Vec<u8>)>) -> Result<(), QrfsError> {
+        for (id, object) in objects.iter() {
+            let object_id = base_id + (*id as u64);
+            let chunks = self.split_object(object_id, object);
+            let count = chunks.len() as u32;
+
+            for chunk in chunks {
+                let qr_code = QrCode::new(serialize(&chunk).unwrap())
+                    .map_err(|error| QrfsError::Storage(format!("no se pudo generar el QR del objeto {}: {:?}", object_id, error)))?;
+
+                let image_qr = qr_code.render::<Luma<u8>>().build();
+                let path = directory.join(format!("{}{}_{}.png", prefix, id, chunk.index));
+                image_qr.save(&path)
+                    .map_err(|error| QrfsError::Storage(format!("no se pudo guardar {:?}: {}", path, error)))?;
+            }
+
+            self.remove_stale_chunks(directory, prefix, *id, count);
+        }
+
+        Ok(())
+    }
+
+    /*
+        Función que convierte los QR a inodes y memory blocks
+        los fragmentos se agrupan por objeto y se reensamblan sin importar el orden de las imágenes
+        E: un arreglo con los paths a los archivos seleccionados por el usuario
+        S: un arreglo de los objetos decodificados
+    */
+    fn translate_inodes_qr(&self, paths: Vec<PathBuf>) -> Vec<QrObject> {
+        let mut chunks: BTreeMap<u64, Vec<QrChunk>> = BTreeMap::new();
+        for path in paths.iter() {
+            let image_qr = match image::open(path) {
+                Ok(image_qr) => image_qr,
+                Err(error) => {
+                    log(LogLevel::Error, &format!("no se pudo abrir la imagen {:?}: {}", path, error));
+                    continue;
                 }
-            },
-            None => reply.error(ENOENT)
+            };
+            let gray_image = image_qr.into_luma8();
+
+            let mut decoder = quircs::Quirc::default();
+
+            let with_image = gray_image.width() as usize;
+            let height_image = gray_image.height() as usize;
+            let data = decoder.identify(with_image, height_image, &gray_image);
+
+            for element in data {
+                let decoded = match element.map(|code| code.decode()) {
+                    Ok(Ok(decoded)) => decoded,
+                    _ => {
+                        log(LogLevel::Error, &format!("no se pudo decodificar un QR de {:?}", path));
+                        continue;
+                    }
+                };
+
+                match deserialize::<QrChunk>(&decoded.payload[..]) {
+                    Ok(chunk) => chunks.entry(chunk.object_id).or_default().push(chunk),
+                    Err(_) => log(LogLevel::Error, &format!("el QR {:?} no contiene un fragmento válido", path))
+                }
+            }
         }
+
+        let mut objects: Vec<QrObject> = Vec::new();
+        for (object_id, chunks) in chunks {
+            if let Some(object) = self.join_chunks(object_id, chunks) {
+                match self.decode_object(&object) {
+                    Ok(object) => objects.push(object),
+                    Err(_) => log(LogLevel::Error, &format!("el objeto {} de los QR está corrupto", object_id))
+                }
+            }
+        }
+
+        objects
     }
 
     /*
-        Función access del filesystem
-        
-        sirve para checar por los permisos de un archivo de fs
+        Función que restaura en el disco los inodes y memory blocks guardados en QR
+        cada inode vuelve a su mismo ino (incluido el root) para conservar la jerarquía
+        E: un arreglo con los paths a las imágenes QR
+        S: N/A
     */
-    fn access(&mut self, _req: &Request, _ino: u64, _mask: u32, reply: ReplyEmpty) {
-        println!("Operation: access");
-        reply.ok();
+    fn load_qr(&mut self, paths: Vec<PathBuf>) {
+        let objects = self.translate_inodes_qr(paths);
+        let mut bitmaps_loaded = false;
+
+        for object in objects {
+            match object {
+                QrObject::Inode(inode) => {
+                    let ino = inode.attributes.ino as usize;
+                    self.grow_inodes(ino);
+
+                    if ino == 0 || ino > self.super_block.len() {
+                        log(LogLevel::Error, &format!("el inode {} no cabe en el disco", ino));
+                        continue;
+                    }
+
+                    if let Err(error) = self.write_inode(inode) {
+                        log(LogLevel::Error, &format!("el inode {} no se pudo restaurar: {}", ino, error));
+                    }
+                },
+                QrObject::Block(position, memory_block) => {
+                    if position >= self.memory_blocks.len() {
+                        log(LogLevel::Error, &format!("el memory block {} no cabe en el disco", position));
+                        continue;
+                    }
+
+                    match memory_block.data {
+                        Some(content) => if let Err(error) = self.write_content_bytes(position, content) {
+                            log(LogLevel::Error, &format!("el memory block {} no se pudo restaurar: {}", position, error));
+                        },
+                        None => log(LogLevel::Error, &format!("el memory block {} está vacío", position))
+                    }
+                },
+                QrObject::Bitmaps(inode_bitmap, block_bitmap) => {
+                    self.grow_inodes(inode_bitmap.size);
+
+                    if inode_bitmap.size != self.inode_bitmap.size || block_bitmap.size != self.block_bitmap.size {
+                        log(LogLevel::Error, "los bitmaps no corresponden al tamaño del disco");
+                        continue;
+                    }
+
+                    self.inode_bitmap = inode_bitmap;
+                    self.block_bitmap = block_bitmap;
+                    bitmaps_loaded = true;
+                }
+            }
+        }
+
+        if bitmaps_loaded {
+            self.remove_freed_objects();
+        }
+
+        self.remove_lost_entries();
+        self.remove_orphans();
+        log(LogLevel::Info, "QR cargados correctamente");
     }
 
     /*
-        Función fsync del filesystem
-        
-        sirve para sincronizar los archivos del fs
+        Función que descarta los inodes y memory blocks que los bitmaps marcan como libres,
+        son imágenes viejas de objetos que se liberaron después de guardarse
+        E: N/A
+        S: N/A
     */
-    fn fsync(&mut self, _req: &Request, _ino: u64, _fh: u64, _datasync: bool, reply: ReplyEmpty) {
-        println!("Operation: fsync");
-        reply.error(ENOSYS);
+    fn remove_freed_objects(&mut self) {
+        for position in 1..self.super_block.len() {
+            if self.super_block[position].is_some() && !self.inode_bitmap.is_set(position) {
+                self.super_block[position] = None;
+            }
+        }
+
+        for position in 0..self.memory_blocks.len() {
+            if self.memory_blocks[position].data.is_some() && !self.block_bitmap.is_set(position) {
+                self.memory_blocks[position] = MemoryBlock { data: None };
+            }
+        }
     }
 
     /*
-        Función opendir del filesystem
-        
-        sirve para abrir una carpeta del fs
+        Función que elimina de las carpetas las entradas a inodes que no se restauraron
+        evita que una imagen faltante deje entradas inválidas en el árbol
+        E: N/A
+        S: N/A
     */
-    fn opendir(&mut self, _req: &Request, ino: u64, flags: u32, reply: ReplyOpen) {
-        println!("Operation: opendir");
-        
-        let inode = self.disk.get_inode(ino);
+    fn remove_lost_entries(&mut self) {
+        for position in 0..self.super_block.len() {
+            let ino = (position + 1) as u64;
+            let entries = match &self.super_block[position] {
+                Some(inode) if inode.attributes.kind == FileType::Directory => self.get_entries(ino),
+                _ => continue
+            };
+
+            let lost: Vec<String> = match entries {
+                Ok(entries) => entries.iter()
+                    .flatten()
+                    .filter(|entry| self.get_inode(entry.ino).is_none())
+                    .map(|entry| entry.name.clone())
+                    .collect(),
+                Err(error) => {
+                    log(LogLevel::Error, &format!("las entradas de la carpeta {} no se pueden leer: {}", ino, error));
+                    continue;
+                }
+            };
 
-        match inode {
-            Some(inode) => reply.opened(inode.attributes.ino, flags),
-            None => reply.error(EISDIR)
+            for name in lost {
+                log(LogLevel::Error, &format!("el inode de {} no se restauró, se elimina su entrada", name));
+                if let Err(error) = self.remove_entry(ino, &name) {
+                    log(LogLevel::Error, &format!("no se pudo eliminar la entrada {}: {}", name, error));
+                }
+            }
         }
     }
-}
 
-fn main() {
-    let mountpoint = match env::args().nth(1) {
-        Some(path) => path,
-        None => {
-            println!("Error: se debe ingresar un mountpoint");
-            return ;
+    /*
+        Función que libera los archivos sin links que se guardaron mientras seguían abiertos,
+        después de cargar nadie los tiene abiertos así que ya no se pueden usar
+        E: N/A
+        S: N/A
+    */
+    fn remove_orphans(&mut self) {
+        let orphans: Vec<u64> = self.super_block.iter()
+            .flatten()
+            .filter(|inode| inode.attributes.kind != FileType::Directory && inode.attributes.nlink == 0)
+            .map(|inode| inode.attributes.ino)
+            .collect();
+
+        for ino in orphans {
+            log(LogLevel::Info, &format!("el inode {} no tiene links, se libera", ino));
+            self.remove_inode(ino);
         }
-    };
+    }
 
-    let fs = QRFS::new(mountpoint.clone());
+    /*
+        Función que obtiene las imágenes QR de una carpeta
+        E: directory (carpeta con las imágenes)
+        S: un arreglo ordenado con los paths a las imágenes PNG de la carpeta
+    */
+    fn read_qr_directory(&self, directory: &Path) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = Vec::new();
+
+        match fs::read_dir(directory) {
+            Ok(entries) => {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    if path.extension().is_some_and(|extension| extension == "png") {
+                        paths.push(path);
+                    }
+                }
+            },
+            Err(error) => log(LogLevel::Error, &format!("no se pudo leer la carpeta {:?}: {}", directory, error))
+        }
 
-    let options = ["-o", "nonempty"]
-        .iter()
-        .map(|o| o.as_ref())
-        .collect::<Vec<&OsStr>>();
-    
-    println!("QRFS iniciado");
-    
-    fuse::mount(fs, &mountpoint, &options);
-}
\ No newline at end of file
+        paths.sort();
+        paths
+    }
+
+    /*
+        Función que despliega un dialogo de selección de archivos al usuario
+        E: directory (carpeta inicial del dialogo)
+        S: un arreglo con los paths a los archivos seleccionados por el usuario
+    */
+    fn display_dialog(&self, directory: &Path) -> Vec<PathBuf> {
+        let paths = FileDialog::new()
+            .set_location(directory)
+            .add_filter("PNG Image", &["png"])
+            .show_open_multiple_file()
+            .unwrap_or_else(|error| {
+                log(LogLevel::Error, &format!("no se pudo abrir el diálogo: {:?}", error));
+                Vec::new()
+            });
+
+        log(LogLevel::Info, &format!("El path es {:?}", paths));
+        paths
+    }
+
+}
+
+/*
+    Estructura de inode para guardar los datos de los archivos y carpetas
+    implementa serialize y deserialize
+*/
+#[derive(Serialize, Deserialize, Clone)]
+struct Inode {
+    #[serde(with = "FileAttrDef")]
+    attributes: FileAttr,
+    references: Vec<Option<usize>>,
+    link_target: Option<Vec<u8>>,
+    xattrs: BTreeMap<String, Vec<u8>>
+}
+
+/*
+    Estructura de una entrada de carpeta, relaciona un nombre con un inode
+    varios nombres pueden apuntar al mismo inode (hard links)
+    implementa serialize y deserialize
+*/
+#[derive(Serialize, Deserialize, Clone)]
+struct DirectoryEntry {
+    name: String,
+    ino: u64
+}
+
+/*
+    Estructura de memory block para guardar el contenido de los archivos
+    implementa serialize y deserialize
+*/
+#[derive(Serialize, Deserialize, Clone)]
+struct MemoryBlock {
+    data: Option<Vec<u8>>
+}
+
+/*
+    Estructura de los objetos que se guardan dentro de un QR
+    puede ser un inode o un memory block junto a su posición en el disco
+    implementa serialize y deserialize
+*/
+#[derive(Serialize, Deserialize)]
+enum QrObject {
+    Inode(Inode),
+    Block(usize, MemoryBlock),
+    Bitmaps(Bitmap, Bitmap)
+}
+
+/*
+    Estructura de bitmap para llevar el control de los espacios libres del disco
+    next guarda la palabra donde continúa la búsqueda, así reservar es O(1) amortizado
+    implementa serialize y deserialize
+*/
+#[derive(Serialize, Deserialize, Clone)]
+struct Bitmap {
+    bits: Vec<u64>,
+    size: usize,
+    free: usize,
+    next: usize
+}
+
+impl Bitmap {
+    /*
+        Función que crea un bitmap con todos los espacios libres
+        E: size (cantidad de espacios)
+        S: un nuevo Bitmap
+    */
+    fn new(size: usize) -> Bitmap {
+        Bitmap {
+            bits: vec![0; size.div_ceil(64)],
+            size,
+            free: size,
+            next: 0
+        }
+    }
+
+    /*
+        Función que indica si un espacio está ocupado
+        E: position (posición del espacio)
+        S: true si está ocupado
+    */
+    fn is_set(&self, position: usize) -> bool {
+        self.bits[position / 64] & (1 << (position % 64)) != 0
+    }
+
+    /*
+        Función que marca un espacio como ocupado
+        E: position (posición del espacio)
+        S: N/A
+    */
+    fn set(&mut self, position: usize) {
+        if !self.is_set(position) {
+            self.bits[position / 64] |= 1 << (position % 64);
+            self.free -= 1;
+        }
+    }
+
+    /*
+        Función que marca un espacio como libre
+        E: position (posición del espacio)
+        S: N/A
+    */
+    fn clear(&mut self, position: usize) {
+        if self.is_set(position) {
+            self.bits[position / 64] &= !(1 << (position % 64));
+            self.free += 1;
+            self.next = self.next.min(position / 64);
+        }
+    }
+
+    /*
+        Función que agranda el bitmap, los espacios nuevos quedan libres
+        E: size (cantidad de espacios nueva)
+        S: N/A
+    */
+    fn grow(&mut self, size: usize) {
+        if size <= self.size {
+            return ;
+        }
+
+        self.bits.resize(size.div_ceil(64), 0);
+        self.free += size - self.size;
+        self.size = size;
+    }
+
+    /*
+        Función que reserva el siguiente espacio libre
+        E: N/A
+        S: un opcional de la posición reservada, None si no quedan espacios libres
+    */
+    fn allocate(&mut self) -> Option<usize> {
+        if self.free == 0 {
+            return None;
+        }
+
+        let words = self.bits.len();
+        for offset in 0..words {
+            let word = (self.next + offset) % words;
+
+            if self.bits[word] != u64::MAX {
+                let position = word * 64 + self.bits[word].trailing_ones() as usize;
+
+                if position < self.size {
+                    self.set(position);
+                    self.next = word;
+                    return Some(position);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/*
+    Estructura de un fragmento de un objeto guardado en QR
+    object_id identifica al objeto, index y count permiten reensamblarlo en orden
+    y checksum valida el objeto completo
+    implementa serialize y deserialize
+*/
+#[derive(Serialize, Deserialize)]
+struct QrChunk {
+    object_id: u64,
+    index: u32,
+    count: u32,
+    checksum: u32,
+    data: Vec<u8>
+}
+
+/*
+    Función que calcula el checksum (Adler-32) de un arreglo de bytes
+    E: data (bytes a verificar)
+    S: el checksum de los bytes
+*/
+fn checksum(data: &[u8]) -> u32 {
+    let mut a: u32 = 1;
+    let mut b: u32 = 0;
+
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+
+    (b << 16) | a
+}
+
+/*
+    Estructura auxiliar de Timespec
+    es necesaria ya que serialize y deserialize no puede tener estructuras dentro de estructuras
+    implementa serialize y deserialize
+*/
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Timespec")]
+pub struct TimespecDef {
+    pub sec: i64,
+    pub nsec: i32,
+}
+
+/*
+    Estructura auxiliar de FileType
+    es necesaria ya que serialize y deserialize no puede tener estructuras dentro de estructuras
+    implementa serialize y deserialize
+*/
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "FileType")]
+pub enum FileTypeDef {
+    NamedPipe,
+    CharDevice,
+    BlockDevice,
+    Directory,
+    RegularFile,
+    Symlink,
+    Socket,
+}
+
+/*
+    Estructura auxiliar de FileAttr
+    es necesaria ya que serialize y deserialize no puede tener estructuras dentro de estructuras
+    implementa serialize y deserialize
+*/
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "FileAttr")]
+pub struct FileAttrDef {
+    pub ino: u64,
+    pub size: u64,
+    pub blocks: u64,
+    #[serde(with = "TimespecDef")]
+    pub atime: Timespec,
+    #[serde(with = "TimespecDef")]
+    pub mtime: Timespec,
+    #[serde(with = "TimespecDef")]
+    pub ctime: Timespec,
+    #[serde(with = "TimespecDef")]
+    pub crtime: Timespec,
+    #[serde(with = "FileTypeDef")]
+    pub kind: FileType,
+    pub perm: u16,
+    pub nlink: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub rdev: u32,
+    pub flags: u32,
+}
+
+/*
+    Niveles del registro de operaciones, cada nivel incluye a los anteriores
+*/
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug
+}
+
+impl LogLevel {
+    /*
+        Función que interpreta el nombre de un nivel
+        E: text (off, error, warn, info o debug)
+        S: un opcional del nivel
+    */
+    fn parse(text: &str) -> Option<LogLevel> {
+        match text.to_lowercase().as_str() {
+            "off" => Some(LogLevel::Off),
+            "error" => Some(LogLevel::Error),
+            "warn" => Some(LogLevel::Warn),
+            "info" => Some(LogLevel::Info),
+            "debug" => Some(LogLevel::Debug),
+            _ => None
+        }
+    }
+
+    /*
+        Función que regresa el nombre del nivel como se escribe en el registro
+        E: N/A
+        S: el nombre del nivel
+    */
+    fn name(&self) -> &'static str {
+        match self {
+            LogLevel::Off => "off",
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug"
+        }
+    }
+}
+
+/*
+    Estructura del registro, escribe una línea clave=valor por mensaje
+    en el archivo de --log-file o en stderr si no hay archivo
+*/
+struct Logger {
+    level: LogLevel,
+    file: Option<Mutex<fs::File>>
+}
+
+impl Logger {
+    /*
+        Función que indica si un nivel se escribe en el registro
+        E: level (nivel del mensaje)
+        S: true si el mensaje se debe escribir
+    */
+    fn enabled(&self, level: LogLevel) -> bool {
+        level != LogLevel::Off && level <= self.level
+    }
+
+    /*
+        Función que escribe un mensaje en el registro con la hora y el nivel
+        E: level (nivel del mensaje) y message (campos clave=valor)
+        S: N/A
+    */
+    fn write(&self, level: LogLevel, message: &str) {
+        if !self.enabled(level) {
+            return ;
+        }
+
+        let now = get_time();
+        let line = format!("ts={}.{:06} level={} {}", now.sec, now.nsec / 1000, level.name(), message);
+
+        match &self.file {
+            Some(file) => if let Ok(mut file) = file.lock() {
+                //Si no se puede escribir el registro no hay otro lugar donde reportarlo
+                let _ = writeln!(file, "{}", line);
+            },
+            None => eprintln!("{}", line)
+        }
+    }
+}
+
+/*
+    Función que configura el registro global, se llama una sola vez al iniciar
+    E: config (configuración con el nivel y el archivo del registro)
+    S: un resultado con el mensaje de error si no se pudo abrir el archivo
+*/
+fn init_logger(config: &Config) -> Result<(), String> {
+    let file = match &config.log_file {
+        Some(path) => match fs::OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => Some(Mutex::new(file)),
+            Err(error) => return Err(format!("no se pudo abrir el registro {:?}: {}", path, error))
+        },
+        None => None
+    };
+
+    LOGGER.set(Logger { level: config.log_level, file })
+        .map_err(|_| String::from("el registro ya estaba configurado"))
+}
+
+/*
+    Función que regresa el registro global, si no se configuró solo escribe advertencias y errores en stderr
+    E: N/A
+    S: el registro
+*/
+fn logger() -> &'static Logger {
+    LOGGER.get_or_init(|| Logger { level: LogLevel::Warn, file: None })
+}
+
+/*
+    Función que escribe un mensaje en el registro global como el campo msg entre comillas
+    E: level (nivel del mensaje) y message (texto del mensaje)
+    S: N/A
+*/
+fn log(level: LogLevel, message: &str) {
+    let logger = logger();
+    if logger.enabled(level) {
+        logger.write(level, &format!("msg={:?}", message));
+    }
+}
+
+/*
+    Respuestas de fuse que pueden terminar en error, permite que Operation
+    registre el errno antes de responder
+*/
+trait ReplyError {
+    fn reply_error(self, errno: c_int);
+}
+
+impl ReplyError for ReplyEntry {
+    fn reply_error(self, errno: c_int) { self.error(errno) }
+}
+
+impl ReplyError for ReplyAttr {
+    fn reply_error(self, errno: c_int) { self.error(errno) }
+}
+
+impl ReplyError for ReplyEmpty {
+    fn reply_error(self, errno: c_int) { self.error(errno) }
+}
+
+impl ReplyError for ReplyOpen {
+    fn reply_error(self, errno: c_int) { self.error(errno) }
+}
+
+impl ReplyError for ReplyData {
+    fn reply_error(self, errno: c_int) { self.error(errno) }
+}
+
+impl ReplyError for ReplyDirectory {
+    fn reply_error(self, errno: c_int) { self.error(errno) }
+}
+
+impl ReplyError for ReplyWrite {
+    fn reply_error(self, errno: c_int) { self.error(errno) }
+}
+
+impl ReplyError for ReplyCreate {
+    fn reply_error(self, errno: c_int) { self.error(errno) }
+}
+
+impl ReplyError for ReplyXattr {
+    fn reply_error(self, errno: c_int) { self.error(errno) }
+}
+
+/*
+    Estructura de una operación de fuse en curso, al terminar el callback
+    escribe una línea con los campos, el resultado (errno) y la latencia
+*/
+struct Operation {
+    name: &'static str,
+    fields: Vec<(&'static str, String)>,
+    start: Instant,
+    errno: Cell<c_int>
+}
+
+impl Operation {
+    /*
+        Función que inicia el registro de una operación
+        E: name (nombre de la operación)
+        S: la operación
+    */
+    fn start(name: &'static str) -> Operation {
+        Operation { name, fields: Vec::new(), start: Instant::now(), errno: Cell::new(0) }
+    }
+
+    /*
+        Función que agrega un campo a la operación, solo se guarda si el registro lo va a escribir
+        E: key (nombre del campo) y value (valor del campo)
+        S: la operación con el campo
+    */
+    fn field<T: fmt::Display>(mut self, key: &'static str, value: T) -> Operation {
+        if logger().enabled(LogLevel::Info) {
+            self.fields.push((key, value.to_string()));
+        }
+        self
+    }
+
+    /*
+        Función que agrega un nombre de archivo a la operación entre comillas
+        E: key (nombre del campo) y value (nombre recibido de fuse)
+        S: la operación con el campo
+    */
+    fn name(self, key: &'static str, value: &OsStr) -> Operation {
+        if !logger().enabled(LogLevel::Info) {
+            return self;
+        }
+
+        let value = format!("{:?}", value.to_string_lossy());
+        self.field(key, value)
+    }
+
+    /*
+        Función que responde un error a fuse y lo guarda como resultado de la operación,
+        el errno siempre sale de QrfsError::errno para que la traducción esté en un solo lugar
+        E: reply (respuesta de fuse) y error (error de la operación)
+        S: N/A
+    */
+    fn fail<R: ReplyError>(&self, reply: R, error: QrfsError) {
+        let errno = error.errno();
+        self.errno.set(errno);
+        reply.reply_error(errno);
+    }
+}
+
+impl Drop for Operation {
+    /*
+        Función drop de la operación
+
+        escribe la operación en el registro, las exitosas en debug, los errores de entrada y salida
+        en error y los demás errores en info
+    */
+    fn drop(&mut self) {
+        let errno = self.errno.get();
+        let level = match errno {
+            0 => LogLevel::Debug,
+            EIO => LogLevel::Error,
+            _ => LogLevel::Info
+        };
+
+        let logger = logger();
+        if !logger.enabled(level) {
+            return ;
+        }
+
+        let mut message = format!("op={}", self.name);
+        for (key, value) in self.fields.iter() {
+            message.push_str(&format!(" {}={}", key, value));
+        }
+
+        if errno == 0 {
+            message.push_str(" result=ok");
+        } else {
+            message.push_str(&format!(" result=error errno={}", errno));
+        }
+
+        message.push_str(&format!(" latency_us={}", self.start.elapsed().as_micros()));
+        logger.write(level, &message);
+    }
+}
+
+/*
+    Estructura de la configuración del filesystem
+    se obtiene de los argumentos de la línea de comandos
+*/
+struct Config {
+    mountpoint: String,
+    load_path: Option<PathBuf>,
+    save_path: Option<PathBuf>,
+    qr_path: PathBuf,
+    no_save: bool,
+    interactive: bool,
+    autosave: Option<u64>,
+    log_level: LogLevel,
+    log_file: Option<PathBuf>
+}
+
+impl Config {
+    /*
+        Función que interpreta los argumentos de la línea de comandos
+        E: los argumentos (sin el nombre del programa)
+        S: la configuración o un mensaje de error
+    */
+    fn from_args(args: Vec<String>) -> Result<Config, String> {
+        let mut mountpoint: Option<String> = None;
+        let mut load_path: Option<PathBuf> = None;
+        let mut save_path: Option<PathBuf> = None;
+        let mut qr_path: PathBuf = match env::var(QR_PATH_VARIABLE) {
+            Ok(path) => PathBuf::from(path),
+            Err(_) => PathBuf::from(DEFAULT_QR_PATH)
+        };
+        let mut no_save = false;
+        let mut interactive = false;
+        let mut autosave: Option<u64> = None;
+        let mut log_level = match env::var(LOG_LEVEL_VARIABLE) {
+            Ok(level) => LogLevel::parse(&level).ok_or(format!("{} tiene un nivel desconocido {}", LOG_LEVEL_VARIABLE, level))?,
+            Err(_) => LogLevel::Warn
+        };
+        let mut log_file: Option<PathBuf> = None;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--load" => match args.next() {
+                    Some(path) => load_path = Some(PathBuf::from(path)),
+                    None => return Err(String::from("--load necesita una carpeta"))
+                },
+                "--save-on-exit" => match args.next() {
+                    Some(path) => save_path = Some(PathBuf::from(path)),
+                    None => return Err(String::from("--save-on-exit necesita una carpeta"))
+                },
+                "--qr-dir" => match args.next() {
+                    Some(path) => qr_path = PathBuf::from(path),
+                    None => return Err(String::from("--qr-dir necesita una carpeta"))
+                },
+                "--autosave" => match args.next().and_then(|seconds| seconds.parse::<u64>().ok()) {
+                    Some(seconds) if seconds > 0 => autosave = Some(seconds),
+                    _ => return Err(String::from("--autosave necesita una cantidad de segundos mayor a 0"))
+                },
+                "--log-level" => match args.next().as_deref().and_then(LogLevel::parse) {
+                    Some(level) => log_level = level,
+                    None => return Err(String::from("--log-level necesita un nivel (off, error, warn, info o debug)"))
+                },
+                "--log-file" => match args.next() {
+                    Some(path) => log_file = Some(PathBuf::from(path)),
+                    None => return Err(String::from("--log-file necesita un archivo"))
+                },
+                "--no-save" => no_save = true,
+                "--interactive" => interactive = true,
+                _ if arg.starts_with("--") => return Err(format!("opción desconocida {}", arg)),
+                _ => match mountpoint {
+                    None => mountpoint = Some(arg),
+                    Some(_) => return Err(format!("argumento inesperado {}", arg))
+                }
+            }
+        }
+
+        if autosave.is_some() && (save_path.is_none() || no_save) {
+            return Err(String::from("--autosave necesita --save-on-exit"));
+        }
+
+        match mountpoint {
+            Some(mountpoint) => Ok(Config { mountpoint, load_path, save_path, qr_path, no_save, interactive, autosave, log_level, log_file }),
+            None => Err(String::from("se debe ingresar un mountpoint"))
+        }
+    }
+
+    /*
+        Función que obtiene la carpeta donde fsync y flush guardan los cambios,
+        solo existe cuando se configuró --save-on-exit
+        E: N/A
+        S: un opcional con la carpeta
+    */
+    fn sync_path(&self) -> Option<&Path> {
+        if self.no_save {
+            return None;
+        }
+
+        self.save_path.as_deref()
+    }
+}
+
+/*
+    Función que calcula el hash FNV-1a del nombre de una entrada de carpeta
+    E: name (nombre de la entrada)
+    S: el hash del nombre
+*/
+fn hash_name(name: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+
+    for byte in name.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    hash
+}
+
+/*
+    Función que calcula el offset de readdir de una entrada a partir del hash de su nombre,
+    no depende de la posición de la entrada así que no cambia cuando la carpeta crece
+    E: name (nombre de la entrada)
+    S: el offset de la entrada, siempre mayor a 0
+*/
+fn entry_cookie(name: &str) -> i64 {
+    (hash_name(name) >> 2) as i64 + 1
+}
+
+/*
+    Función que convierte el nombre de una entrada recibido por fuse a texto
+    E: name (nombre de la entrada)
+    S: el nombre como texto o InvalidName si no es UTF-8 válido
+*/
+fn entry_name(name: &OsStr) -> Result<&str, QrfsError> {
+    name.to_str().ok_or(QrfsError::InvalidName)
+}
+
+/*
+    Función que calcula los permisos que se necesitan para abrir un archivo con las flags de open
+    E: flags (flags de open)
+    S: la máscara de permisos (R_OK y W_OK) o EINVAL si el modo de acceso no es válido
+*/
+fn open_mask(flags: u32) -> Result<c_int, QrfsError> {
+    let mut mask = match flags as c_int & O_ACCMODE {
+        O_RDONLY => R_OK,
+        O_WRONLY => W_OK,
+        O_RDWR => R_OK | W_OK,
+        _ => return Err(QrfsError::InvalidArgument)
+    };
+
+    if flags as c_int & O_TRUNC != 0 {
+        mask |= W_OK;
+    }
+
+    Ok(mask)
+}
+
+/*
+    Función que revisa si un usuario tiene los permisos pedidos sobre un archivo,
+    root solo necesita que algún bit de ejecución esté activo para X_OK
+    E: attributes (atributos del archivo), uid y gid (del usuario), mask (R_OK, W_OK y X_OK)
+    S: true si el usuario tiene todos los permisos pedidos
+*/
+fn has_permission(attributes: &FileAttr, uid: u32, gid: u32, mask: c_int) -> bool {
+    let perm = attributes.perm as c_int;
+
+    if uid == 0 {
+        return mask & X_OK == 0 || attributes.kind == FileType::Directory || perm & 0o111 != 0;
+    }
+
+    let granted = if uid == attributes.uid {
+        perm >> 6
+    } else if gid == attributes.gid {
+        perm >> 3
+    } else {
+        perm
+    };
+
+    granted & mask & 0o7 == mask & 0o7
+}
+
+/*
+    Función que revisa si se le puede preguntar algo al usuario,
+    ncurses necesita que la entrada estándar sea una terminal
+    E: N/A
+    S: true si la entrada estándar es una terminal y no se está terminando por una señal
+*/
+fn can_ask_user() -> bool {
+    !TERMINATING.load(Ordering::SeqCst) && unsafe { isatty(STDIN_FILENO) } == 1
+}
+
+/*
+    Función que pregunta al usuario por medio de ncurses
+    E: question (pregunta a desplegar)
+    S: true si el usuario responde Y
+*/
+fn ask_user(question: &str) -> bool {
+    initscr();
+    addstr(question);
+
+    refresh();
+
+    let response = getch();
+    clear();
+    endwin();
+
+    response == 89
+}
+
+/*
+    Estructura del filesystem
+*/
+#[allow(clippy::upper_case_acronyms)]
+struct QRFS {
+    disk: Disk,
+    config: Config,
+    open_files: HashMap<u64, u32>,
+    last_save: Instant,
+    base_saved: bool
+}
+
+impl QRFS {
+    /*
+        Función que crea un nuevo QRFS
+        E: la configuración del filesystem
+        S: una estructura QRFS
+    */
+    fn new(config: Config) -> Self {
+        let max_files: usize = DEFAULT_SIZE;
+        let memory_size: usize = DEFAULT_SIZE * DEFAULT_SIZE * DEFAULT_SIZE;
+        let block_size: usize = max_files * (mem::size_of::<Vec<Inode>>() + mem::size_of::<Inode>());
+
+        let mut disk = Disk::new(config.mountpoint.clone(), memory_size, block_size);
+
+        match &config.load_path {
+            Some(directory) => {
+                let paths = disk.read_qr_directory(directory);
+                disk.load_qr(paths);
+            },
+            None => {
+                if config.interactive && can_ask_user() && ask_user("¿Desea seleccionar archivos previos? \nY = sí\nCualquiera = no\n") {
+                    let paths = disk.display_dialog(&config.qr_path);
+                    disk.load_qr(paths);
+                }
+            }
+        }
+
+        disk.clear_changes();
+        let base_saved = config.load_path.is_some() && config.load_path == config.save_path;
+
+        QRFS { disk, config, open_files: HashMap::new(), last_save: Instant::now(), base_saved }
+    }
+
+    /*
+        Función que revisa si se puede usar un atributo extendido, solo se soportan los del
+        espacio "user." y únicamente en archivos regulares y carpetas
+        E: req (petición de fuse), ino (identificador del inode), name (nombre del atributo), mask (R_OK o W_OK)
+        S: Ok si se puede usar, el código de error si no
+    */
+    fn check_xattr(&self, req: &Request, ino: u64, name: &str, mask: c_int) -> Result<(), QrfsError> {
+        if !name.starts_with(XATTR_USER_PREFIX) {
+            return Err(QrfsError::NotSupported);
+        }
+
+        match self.disk.get_inode(ino) {
+            Some(inode) if inode.attributes.kind != FileType::RegularFile && inode.attributes.kind != FileType::Directory => Err(QrfsError::NotPermitted),
+            Some(_) => self.check_permission(req, ino, mask),
+            None => Err(QrfsError::NotFound)
+        }
+    }
+
+    /*
+        Función que abre un archivo que ya existe cuando create no pide O_EXCL,
+        con O_TRUNC el archivo se vacía como en open
+        E: req (petición de fuse), ino (identificador del inode) y flags (flags de open)
+        S: los atributos del archivo abierto o el código de error
+    */
+    fn open_existing(&mut self, req: &Request, ino: u64, flags: u32) -> Result<FileAttr, QrfsError> {
+        if flags as c_int & O_EXCL != 0 {
+            return Err(QrfsError::AlreadyExists);
+        }
+
+        match self.disk.get_inode(ino) {
+            Some(inode) if inode.attributes.kind == FileType::Directory => return Err(QrfsError::IsDirectory),
+            Some(_) => (),
+            None => return Err(QrfsError::NotFound)
+        }
+
+        self.check_permission(req, ino, open_mask(flags)?)?;
+
+        if flags as c_int & O_TRUNC != 0 {
+            self.disk.truncate_file(ino, 0)?;
+        }
+
+        self.open_file(ino);
+        self.disk.get_inode(ino).map(|inode| inode.attributes).ok_or(QrfsError::NotFound)
+    }
+
+    /*
+        Función que revisa los permisos del usuario que hace la petición sobre un inode
+        E: req (petición de fuse), ino (identificador del inode), mask (R_OK, W_OK y X_OK)
+        S: Ok si tiene permiso, ENOENT si no existe el inode o EACCES si no tiene permiso
+    */
+    fn check_permission(&self, req: &Request, ino: u64, mask: c_int) -> Result<(), QrfsError> {
+        match self.disk.get_inode(ino) {
+            Some(inode) if has_permission(&inode.attributes, req.uid(), req.gid(), mask) => Ok(()),
+            Some(_) => Err(QrfsError::PermissionDenied),
+            None => Err(QrfsError::NotFound)
+        }
+    }
+
+    /*
+        Función que revisa si el usuario puede quitar un nombre de una carpeta,
+        si la carpeta tiene el sticky bit solo el dueño del archivo o de la carpeta puede hacerlo
+        E: req (petición de fuse), parent (carpeta), ino (inode al que apunta el nombre)
+        S: Ok si puede quitarlo, EACCES o EPERM si no
+    */
+    fn check_removal(&self, req: &Request, parent: u64, ino: u64) -> Result<(), QrfsError> {
+        self.check_permission(req, parent, W_OK | X_OK)?;
+
+        let uid = req.uid();
+        if uid == 0 {
+            return Ok(());
+        }
+
+        let parent_attributes = match self.disk.get_inode(parent) {
+            Some(inode) => inode.attributes,
+            None => return Err(QrfsError::NotFound)
+        };
+
+        if parent_attributes.perm as u32 & S_ISVTX == 0 || parent_attributes.uid == uid {
+            return Ok(());
+        }
+
+        match self.disk.get_inode(ino) {
+            Some(inode) if inode.attributes.uid == uid => Ok(()),
+            Some(_) => Err(QrfsError::NotPermitted),
+            None => Err(QrfsError::NotFound)
+        }
+    }
+
+    /*
+        Función que revisa si una carpeta tiene el bit setgid
+        E: ino (identificador de la carpeta)
+        S: true si los archivos nuevos heredan el grupo de la carpeta
+    */
+    fn has_setgid(&self, ino: u64) -> bool {
+        self.disk.get_inode(ino).is_some_and(|inode| inode.attributes.perm as u32 & S_ISGID != 0)
+    }
+
+    /*
+        Función que calcula el dueño y grupo de un archivo nuevo, si la carpeta
+        padre tiene el bit setgid el archivo hereda el grupo de la carpeta
+        E: req (petición de fuse), parent (carpeta donde se crea el archivo)
+        S: (uid, gid) del archivo nuevo
+    */
+    fn new_owner(&self, req: &Request, parent: u64) -> (u32, u32) {
+        match self.disk.get_inode(parent) {
+            Some(inode) if self.has_setgid(parent) => (req.uid(), inode.attributes.gid),
+            _ => (req.uid(), req.gid())
+        }
+    }
+
+    /*
+        Función que revisa los permisos de un rename, se necesita poder quitar el nombre
+        viejo, escribir en la carpeta destino y reemplazar el destino si ya existe,
+        una carpeta que cambia de padre también necesita permiso de escritura para actualizar ".."
+        E: req (petición de fuse), parent y name (origen), newparent y newname (destino)
+        S: Ok si tiene permiso, el código de error si no
+    */
+    fn check_rename(&self, req: &Request, parent: u64, name: &str, newparent: u64, newname: &str) -> Result<(), QrfsError> {
+        let ino = self.disk.find_entry(parent, name)?.ok_or(QrfsError::NotFound)?;
+
+        self.check_removal(req, parent, ino)?;
+        self.check_permission(req, newparent, W_OK | X_OK)?;
+
+        if let Some(target_ino) = self.disk.find_entry(newparent, newname)? {
+            self.check_removal(req, newparent, target_ino)?;
+        }
+
+        let is_directory = self.disk.get_inode(ino).is_some_and(|inode| inode.attributes.kind == FileType::Directory);
+        if is_directory && parent != newparent {
+            self.check_permission(req, ino, W_OK)?;
+        }
+
+        Ok(())
+    }
+
+    /*
+        Función que registra que un archivo fue abierto
+        E: ino (identificador del inode)
+        S: N/A
+    */
+    fn open_file(&mut self, ino: u64) {
+        *self.open_files.entry(ino).or_insert(0) += 1;
+    }
+
+    /*
+        Función que registra que un archivo fue cerrado
+        si ya no tiene links ni quedan handles abiertos se libera el inode
+        E: ino (identificador del inode)
+        S: N/A
+    */
+    fn close_file(&mut self, ino: u64) {
+        if let Some(count) = self.open_files.get_mut(&ino) {
+            *count -= 1;
+            if *count == 0 {
+                self.open_files.remove(&ino);
+            }
+        }
+
+        self.remove_if_orphan(ino);
+    }
+
+    /*
+        Función que mueve una entrada de una carpeta a otra con la semántica de rename de POSIX
+        si el destino existe se reemplaza, RENAME_NOREPLACE falla si existe
+        y RENAME_EXCHANGE intercambia ambas entradas
+        E: parent y name (entrada origen), newparent y newname (entrada destino) y flags de rename
+        S: un resultado con el errno en caso de fallar
+    */
+    fn rename_entry(&mut self, parent: u64, name: &str, newparent: u64, newname: &str, flags: u32) -> Result<(), QrfsError> {
+        if newname.len() > NAMELEN as usize {
+            return Err(QrfsError::NameTooLong);
+        }
+
+        let (ino, kind) = match self.disk.find_inode_name(parent, name)? {
+            Some(inode) => (inode.attributes.ino, inode.attributes.kind),
+            None => return Err(QrfsError::NotFound)
+        };
+
+        let target = self.disk.find_inode_name(newparent, newname)?
+            .map(|inode| (inode.attributes.ino, inode.attributes.kind));
+
+        if kind == FileType::Directory && self.disk.is_descendant(ino, newparent)? {
+            return Err(QrfsError::InvalidArgument);
+        }
+
+        if flags & RENAME_NOREPLACE != 0 && target.is_some() {
+            return Err(QrfsError::AlreadyExists);
+        }
+
+        if flags & RENAME_EXCHANGE != 0 {
+            let (target_ino, target_kind) = target.ok_or(QrfsError::NotFound)?;
+
+            if target_kind == FileType::Directory && self.disk.is_descendant(target_ino, parent)? {
+                return Err(QrfsError::InvalidArgument);
+            }
+
+            self.disk.set_entry_ino(parent, name, target_ino)?;
+            self.disk.set_entry_ino(newparent, newname, ino)?;
+
+            if parent != newparent {
+                self.disk.move_directory(ino, parent, newparent)?;
+                self.disk.move_directory(target_ino, newparent, parent)?;
+            }
+            return Ok(());
+        }
+
+        match target {
+            Some((target_ino, _)) if target_ino == ino => return Ok(()),
+            Some((_, target_kind)) if kind == FileType::Directory && target_kind != FileType::Directory => return Err(QrfsError::NotDirectory),
+            Some((_, target_kind)) if kind != FileType::Directory && target_kind == FileType::Directory => return Err(QrfsError::IsDirectory),
+            Some((target_ino, FileType::Directory)) if !self.disk.is_empty_directory(target_ino)? => return Err(QrfsError::NotEmpty),
+            _ => ()
+        }
+
+        //El destino se reemplaza en su misma entrada, si no existe se agrega antes de quitar
+        //el nombre viejo para no perder el archivo si la carpeta destino no puede crecer
+        if let Some((target_ino, target_kind)) = target {
+            self.disk.set_entry_ino(newparent, newname, ino)?;
+
+            if target_kind == FileType::Directory {
+                self.disk.decrease_nlink(newparent);
+                self.disk.remove_inode(target_ino);
+            } else {
+                self.disk.decrease_nlink(target_ino);
+                self.remove_if_orphan(target_ino);
+            }
+        } else {
+            self.disk.add_entry(newparent, newname, ino)?;
+        }
+
+        self.disk.remove_entry(parent, name)?;
+        self.disk.move_directory(ino, parent, newparent)?;
+
+        if let Some(inode) = self.disk.get_inode_mutable(ino) {
+            inode.attributes.ctime = get_time();
+        }
+
+        Ok(())
+    }
+
+    /*
+        Función que guarda los cambios pendientes en la carpeta de guardado,
+        la primera vez se guarda el disco completo para que la carpeta quede consistente,
+        fsync y flush la usan también porque los bitmaps guardados deben coincidir con todos los objetos
+        E: N/A
+        S: Ok o el error de guardado
+    */
+    fn save_changes(&mut self) -> Result<(), QrfsError> {
+        let directory = match self.config.sync_path() {
+            Some(directory) => directory,
+            None => return Ok(())
+        };
+
+        let result = if self.base_saved {
+            self.disk.save_changes(directory)
+        } else {
+            self.disk.save(directory).map(|_| self.disk.clear_changes())
+        };
+
+        match result {
+            Ok(()) => {
+                self.base_saved = true;
+                self.last_save = Instant::now();
+                Ok(())
+            },
+            Err(error) => {
+                log(LogLevel::Error, &format!("no se pudieron guardar los cambios: {}", error));
+                Err(error)
+            }
+        }
+    }
+
+    /*
+        Función que guarda los cambios pendientes si ya pasó el intervalo de --autosave,
+        el hilo principal la llama cada segundo mientras el fs está montado
+        E: N/A
+        S: N/A
+    */
+    fn check_autosave(&mut self) {
+        let interval = match self.config.autosave {
+            Some(seconds) => Duration::from_secs(seconds),
+            None => return
+        };
+
+        //save_changes ya reporta el error y los cambios quedan pendientes para el siguiente intento
+        if self.last_save.elapsed() >= interval && self.disk.has_changes() {
+            let _ = self.save_changes();
+        }
+    }
+
+    /*
+        Función que libera un inode que ya no tiene links ni handles abiertos
+        E: ino (identificador del inode)
+        S: N/A
+    */
+    fn remove_if_orphan(&mut self, ino: u64) {
+        if self.open_files.contains_key(&ino) {
+            return ;
+        }
+
+        if let Some(inode) = self.disk.get_inode(ino) {
+            if inode.attributes.nlink == 0 {
+                self.disk.remove_inode(ino);
+            }
+        }
+    }
+}
+
+impl Drop for QRFS {
+    /*
+        Función drop del filesystem
+        
+        sirve para guardar los archivos según la configuración, también se ejecuta
+        cuando se desmonta por una señal, solo le pregunta al usuario en modo interactivo
+    */
+    fn drop(&mut self) {
+        if self.config.no_save {
+            return ;
+        }
+
+        //save_changes ya reporta el error, al desmontar no hay nada más que hacer con él
+        if self.config.save_path.is_some() {
+            let _ = self.save_changes();
+            return ;
+        }
+
+        if !self.config.interactive {
+            return ;
+        }
+
+        //Sin terminal o al terminar por una señal se guarda sin preguntar para no perder los archivos
+        if can_ask_user() && !ask_user("¿Desea guardar los archivos? \nY = sí\nCualquiera = no\n") {
+            return ;
+        }
+
+        if let Err(error) = self.disk.save(&self.config.qr_path) {
+            log(LogLevel::Error, &format!("no se pudo guardar el disco: {}", error));
+        }
+    }
+}
+
+impl Filesystem for QRFS {
+    /*
+        Función lookup del filesystem
+        
+        sirve para detectar los archivos o carpetas pertenecientes del fs
+    */
+    fn lookup(&mut self, req: &Request, parent: u64, name: &std::ffi::OsStr, reply: ReplyEntry) {
+        let operation = Operation::start("lookup").field("parent", parent).name("name", name);
+
+        if let Err(error) = self.check_permission(req, parent, X_OK) {
+            operation.fail(reply, error);
+            return ;
+        }
+
+        let name = match entry_name(name) {
+            Ok(name) => name,
+            Err(error) => {
+                operation.fail(reply, error);
+                return ;
+            }
+        };
+
+        let inode = self.disk.find_inode_name(parent, name);
+
+        match inode {
+            Ok(Some(inode)) => {
+                let ttl = get_time();
+                reply.entry(&ttl, &inode.attributes, 0);
+            },
+            Ok(None) => operation.fail(reply, QrfsError::NotFound),
+            Err(error) => operation.fail(reply, error)
+        }
+    }
+
+    /*
+        Función getattr del filesystem
+        
+        sirve para obtener los atributos de un archivo o carpeta
+    */
+    fn getattr(&mut self, _req: &Request, ino: u64, reply: ReplyAttr) {
+        let operation = Operation::start("getattr").field("ino", ino);
+
+        match self.disk.get_inode(ino) {
+            Some(inode) => {
+                let ttl = get_time();
+                reply.attr(&ttl, &inode.attributes);
+            },
+            None => operation.fail(reply, QrfsError::NotFound)
+        }
+    }
+
+    /*
+        Función setattr del filesystem
+        
+        sirve para cambiar el tamaño, los permisos, el dueño y las fechas de un archivo del fs
+    */
+    fn setattr(&mut self, req: &Request, ino: u64, mode: Option<u32>, uid: Option<u32>, gid: Option<u32>, size: Option<u64>, atime: Option<Timespec>, mtime: Option<Timespec>, _fh: Option<u64>, _crtime: Option<Timespec>, _chgtime: Option<Timespec>, _bkuptime: Option<Timespec>, _flags: Option<u32>, reply: ReplyAttr) {
+        let operation = Operation::start("setattr").field("ino", ino);
+
+        let attributes = match self.disk.get_inode(ino) {
+            Some(inode) => inode.attributes,
+            None => {
+                operation.fail(reply, QrfsError::NotFound);
+                return ;
+            }
+        };
+
+        let caller = req.uid();
+        let is_owner = caller == 0 || caller == attributes.uid;
+
+        let denied = (mode.is_some() && !is_owner)
+            || uid.is_some_and(|uid| caller != 0 && uid != attributes.uid)
+            || gid.is_some_and(|gid| caller != 0 && gid != attributes.gid && (!is_owner || gid != req.gid()));
+
+        if denied {
+            operation.fail(reply, QrfsError::NotPermitted);
+            return ;
+        }
+
+        let needs_write = size.is_some() || ((atime.is_some() || mtime.is_some()) && !is_owner);
+        if needs_write && !has_permission(&attributes, caller, req.gid(), W_OK) {
+            operation.fail(reply, QrfsError::PermissionDenied);
+            return ;
+        }
+
+        if let Some(size) = size {
+            if attributes.kind == FileType::Directory {
+                operation.fail(reply, QrfsError::IsDirectory);
+                return ;
+            }
+
+            if let Err(error) = self.disk.truncate_file(ino, size) {
+                operation.fail(reply, error);
+                return ;
+            }
+        }
+
+        match self.disk.get_inode_mutable(ino) {
+            Some(inode) => {
+                if let Some(mode) = mode {
+                    let mut perm = mode & 0o7777;
+                    if caller != 0 && req.gid() != inode.attributes.gid {
+                        perm &= !S_ISGID;
+                    }
+                    inode.attributes.perm = perm as u16;
+                }
+                if let Some(uid) = uid {
+                    inode.attributes.uid = uid;
+                }
+                if let Some(gid) = gid {
+                    inode.attributes.gid = gid;
+                }
+                if caller != 0 && (uid.is_some() || gid.is_some()) && inode.attributes.kind != FileType::Directory {
+                    inode.attributes.perm &= 0o1777;
+                }
+                if let Some(atime) = atime {
+                    inode.attributes.atime = atime;
+                }
+                if let Some(mtime) = mtime {
+                    inode.attributes.mtime = mtime;
+                }
+
+                let ttl = get_time();
+                inode.attributes.ctime = ttl;
+                reply.attr(&ttl, &inode.attributes);
+            },
+            None => operation.fail(reply, QrfsError::NotFound)
+        }
+    }
+
+    /*
+        Función read del filesystem
+        
+        sirve para leer el contenido de un archivo del fs
+    */
+    fn read(&mut self, _req: &Request, ino: u64, _fh: u64, offset: i64, size: u32, reply: ReplyData) {
+        let operation = Operation::start("read").field("ino", ino).field("offset", offset).field("size", size);
+
+        if offset < 0 {
+            operation.fail(reply, QrfsError::InvalidArgument);
+            return ;
+        }
+
+        match self.disk.get_inode(ino) {
+            Some(inode) if inode.attributes.kind == FileType::Directory => operation.fail(reply, QrfsError::IsDirectory),
+            Some(_) => match self.disk.read_file(ino, offset as usize, size as usize) {
+                Ok(content) => reply.data(&content),
+                Err(error) => operation.fail(reply, error)
+            },
+            None => operation.fail(reply, QrfsError::NotFound)
+        }
+    }
+
+    /*
+        Función readdir del filesystem
+        
+        sirve para detectar los directorios del fs
+    */
+    fn readdir(&mut self, _req: &Request, ino: u64, _fh: u64, offset: i64, mut reply: ReplyDirectory) {
+        let operation = Operation::start("readdir").field("ino", ino).field("offset", offset);
+
+        match self.disk.get_inode(ino) {
+            Some(inode) if inode.attributes.kind != FileType::Directory => {
+                operation.fail(reply, QrfsError::NotDirectory);
+                return ;
+            },
+            Some(_) => (),
+            None => {
+                operation.fail(reply, QrfsError::NotFound);
+                return ;
+            }
+        }
+
+        let entries = match self.disk.get_entries(ino) {
+            Ok(entries) => entries,
+            Err(error) => {
+                operation.fail(reply, error);
+                return ;
+            }
+        };
+
+        //Las entradas se listan ordenadas por el hash de su nombre y ese hash es su offset,
+        //así el kernel continúa donde se quedó aunque la carpeta crezca y las entradas cambien de bloque
+        let mut entries: Vec<(i64, DirectoryEntry)> = entries.into_iter()
+            .flatten()
+            .map(|entry| (entry_cookie(&entry.name), entry))
+            .collect();
+        entries.sort_by(|(cookie, entry), (other_cookie, other)| cookie.cmp(other_cookie).then_with(|| entry.name.cmp(&other.name)));
+
+        for (cookie, entry) in entries.iter().filter(|(cookie, _)| *cookie > offset) {
+            if let Some(data) = self.disk.get_inode(entry.ino) {
+                if reply.add(entry.ino, *cookie, data.attributes.kind, &entry.name) {
+                    break;
+                }
+            }
+        }
+
+        reply.ok();
+    }
+
+    /*
+        Función mkdir del filesystem
+        
+        sirve para crear una nueva carpeta en el fs
+    */
+    fn mkdir(&mut self, req: &Request, parent: u64, name: &OsStr, mode: u32, reply: ReplyEntry) {
+        let operation = Operation::start("mkdir").field("parent", parent).name("name", name);
+
+        let name = match entry_name(name) {
+            Ok(name) => name,
+            Err(error) => {
+                operation.fail(reply, error);
+                return ;
+            }
+        };
+
+        if let Err(error) = self.check_permission(req, parent, W_OK | X_OK) {
+            operation.fail(reply, error);
+            return ;
+        }
+
+        let (uid, gid) = self.new_owner(req, parent);
+        let mut perm = (mode & 0o7777) as u16;
+        //Las subcarpetas de una carpeta setgid también son setgid para que la herencia del grupo continúe
+        if self.has_setgid(parent) {
+            perm |= S_ISGID as u16;
+        }
+
+        let ino = match self.disk.allocate_ino() {
+            Ok(ino) => ino,
+            Err(error) => {
+                operation.fail(reply, error);
+                return ;
+            }
+        };
+
+        let ttl = get_time();
+        let attributes = FileAttr {
+            ino,
+            size: 0,
+            blocks: 0,
+            atime: ttl,
+            mtime: ttl,
+            ctime: ttl,
+            crtime: ttl,
+            kind: FileType::Directory,
+            perm,
+            nlink: 2,
+            uid,
+            gid,
+            rdev: 0,
+            flags: 0
+        };
+
+        let inode = Inode {
+            attributes,
+            references: vec![Option::None; FILE_REFERENCES],
+            link_target: None,
+            xattrs: BTreeMap::new()
+        };
+
+        let result = self.disk.write_inode(inode)
+            .and_then(|_| self.disk.init_directory(ino, parent))
+            .and_then(|_| self.disk.add_entry(parent, name, ino));
+
+        if let Err(error) = result {
+            self.disk.remove_inode(ino);
+            operation.fail(reply, error);
+            return ;
+        }
+
+        self.disk.increase_nlink(parent);
+
+        match self.disk.get_inode(ino) {
+            Some(inode) => reply.entry(&ttl, &inode.attributes, 0),
+            None => operation.fail(reply, QrfsError::NotFound)
+        }
+    }
+
+    /*
+        Función create del filesystem
+        
+        sirve para crear un nuevo archivo en el fs, si el nombre ya existe se abre
+        el archivo existente salvo que se pida O_EXCL
+    */
+    fn create(&mut self, req: &Request, parent: u64, name: &OsStr, mode: u32, flags: u32, reply: ReplyCreate) {
+        let operation = Operation::start("create").field("parent", parent).name("name", name);
+
+        if let Err(error) = self.check_permission(req, parent, X_OK) {
+            operation.fail(reply, error);
+            return ;
+        }
+
+        let name = match entry_name(name) {
+            Ok(name) => name,
+            Err(error) => {
+                operation.fail(reply, error);
+                return ;
+            }
+        };
+
+        let existing = match self.disk.find_entry(parent, name) {
+            Ok(existing) => existing,
+            Err(error) => {
+                operation.fail(reply, error);
+                return ;
+            }
+        };
+
+        if let Some(ino) = existing {
+            match self.open_existing(req, ino, flags) {
+                Ok(attributes) => reply.created(&get_time(), &attributes, 1, ino, flags),
+                Err(error) => operation.fail(reply, error)
+            }
+            return ;
+        }
+
+        if let Err(error) = self.check_permission(req, parent, W_OK | X_OK) {
+            operation.fail(reply, error);
+            return ;
+        }
+
+        let (uid, gid) = self.new_owner(req, parent);
+
+        let next_ino = match self.disk.allocate_ino() {
+            Ok(next_ino) => next_ino,
+            Err(error) => {
+                operation.fail(reply, error);
+                return ;
+            }
+        };
+
+        let ttl = get_time();
+
+        let attributes = FileAttr {
+            ino: next_ino,
+            size: 0,
+            blocks: 0,
+            atime: ttl,
+            mtime: ttl,
+            ctime: ttl,
+            crtime: ttl,
+            kind: FileType::RegularFile,
+            perm: (mode & 0o7777) as u16,
+            nlink: 1,
+            uid,
+            gid,
+            rdev: 0,
+            flags
+        };
+
+        let inode = Inode {
+            attributes,
+            references: vec![Option::None; FILE_REFERENCES],
+            link_target: None,
+            xattrs: BTreeMap::new()
+        };
+
+        let result = self.disk.write_inode(inode)
+            .and_then(|_| self.disk.add_entry(parent, name, next_ino));
+
+        if let Err(error) = result {
+            self.disk.remove_inode(next_ino);
+            operation.fail(reply, error);
+            return ;
+        }
+        
+        self.open_file(next_ino);
+        reply.created(&ttl, &attributes, 1, next_ino, flags);
+    }
+
+    /*
+        Función symlink del filesystem
+        
+        sirve para crear un link simbólico en el fs, los destinos cortos se guardan
+        dentro del inode y los largos en memory blocks
+    */
+    fn symlink(&mut self, req: &Request, parent: u64, name: &OsStr, link: &Path, reply: ReplyEntry) {
+        let operation = Operation::start("symlink").field("parent", parent).name("name", name);
+
+        let name = match entry_name(name) {
+            Ok(name) => name,
+            Err(error) => {
+                operation.fail(reply, error);
+                return ;
+            }
+        };
+
+        if let Err(error) = self.check_permission(req, parent, W_OK | X_OK) {
+            operation.fail(reply, error);
+            return ;
+        }
+
+        let (uid, gid) = self.new_owner(req, parent);
+
+        let ino = match self.disk.allocate_ino() {
+            Ok(ino) => ino,
+            Err(error) => {
+                operation.fail(reply, error);
+                return ;
+            }
+        };
+
+        let target = link.as_os_str().as_bytes().to_vec();
+        let ttl = get_time();
+        let attributes = FileAttr {
+            ino,
+            size: target.len() as u64,
+            blocks: 0,
+            atime: ttl,
+            mtime: ttl,
+            ctime: ttl,
+            crtime: ttl,
+            kind: FileType::Symlink,
+            perm: 0o777,
+            nlink: 1,
+            uid,
+            gid,
+            rdev: 0,
+            flags: 0
+        };
+
+        let inode = Inode {
+            attributes,
+            references: vec![Option::None; FILE_REFERENCES],
+            link_target: None,
+            xattrs: BTreeMap::new()
+        };
+
+        let result = self.disk.write_inode(inode)
+            .and_then(|_| self.disk.write_link_target(ino, &target));
+
+        if let Err(error) = result {
+            self.disk.remove_inode(ino);
+            operation.fail(reply, error);
+            return ;
+        }
+
+        if let Err(error) = self.disk.add_entry(parent, name, ino) {
+            self.disk.remove_inode(ino);
+            operation.fail(reply, error);
+            return ;
+        }
+
+        match self.disk.get_inode(ino) {
+            Some(inode) => reply.entry(&ttl, &inode.attributes, 0),
+            None => operation.fail(reply, QrfsError::NotFound)
+        }
+    }
+
+    /*
+        Función readlink del filesystem
+        
+        sirve para leer el destino de un link simbólico del fs
+    */
+    fn readlink(&mut self, _req: &Request, ino: u64, reply: ReplyData) {
+        let operation = Operation::start("readlink").field("ino", ino);
+
+        match self.disk.read_link_target(ino) {
+            Ok(target) => reply.data(&target),
+            Err(error) => operation.fail(reply, error)
+        }
+    }
+
+    /*
+        Función open del filesystem
+        
+        sirve para abrir un archivo del fs
+    */
+    fn open(&mut self, req: &Request, ino: u64, flags: u32, reply: ReplyOpen) {
+        let operation = Operation::start("open").field("ino", ino);
+
+        let mask = match open_mask(flags) {
+            Ok(mask) => mask,
+            Err(error) => {
+                operation.fail(reply, error);
+                return ;
+            }
+        };
+
+        match self.check_permission(req, ino, mask) {
+            Ok(()) => {
+                self.open_file(ino);
+                reply.opened(ino, flags);
+            },
+            Err(error) => operation.fail(reply, error)
+        }
+    }
+
+    /*
+        Función release del filesystem
+        
+        sirve para cerrar un archivo del fs
+    */
+    fn release(&mut self, _req: &Request, ino: u64, _fh: u64, flags: u32, _lock_owner: u64, _flush: bool, reply: ReplyEmpty) {
+        let operation = Operation::start("release").field("ino", ino);
+
+        let result = if flags as c_int & O_ACCMODE != O_RDONLY {
+            self.save_changes()
+        } else {
+            Ok(())
+        };
+
+        self.close_file(ino);
+        match result {
+            Ok(()) => reply.ok(),
+            Err(error) => operation.fail(reply, error)
+        }
+    }
+
+    /*
+        Función flush del filesystem
+        
+        sirve para guardar los cambios de un archivo cuando se cierra un descriptor
+    */
+    fn flush(&mut self, _req: &Request, ino: u64, _fh: u64, _lock_owner: u64, reply: ReplyEmpty) {
+        let operation = Operation::start("flush").field("ino", ino);
+
+        match self.save_changes() {
+            Ok(()) => reply.ok(),
+            Err(error) => operation.fail(reply, error)
+        }
+    }
+    
+    /*
+        Función write del filesystem
+        
+        sirve para escribir dentro de un archivo del fs
+    */
+    fn write(&mut self, _req: &Request, ino: u64, _fh: u64, offset: i64, data: &[u8], _flags: u32, reply: ReplyWrite) {
+        let operation = Operation::start("write").field("ino", ino).field("offset", offset).field("size", data.len());
+
+        if offset < 0 {
+            operation.fail(reply, QrfsError::InvalidArgument);
+            return ;
+        }
+
+        match self.disk.write_file(ino, offset as usize, data) {
+            Ok(written) => reply.written(written as u32),
+            Err(error) => operation.fail(reply, error)
+        }
+    }
+
+    /*
+        Función unlink del filesystem
+        
+        sirve para eliminar un archivo del fs, el inode se libera
+        cuando ya no tiene links y nadie lo tiene abierto
+    */
+    fn unlink(&mut self, req: &Request, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        let operation = Operation::start("unlink").field("parent", parent).name("name", name);
+
+        let name = match entry_name(name) {
+            Ok(name) => name,
+            Err(error) => {
+                operation.fail(reply, error);
+                return ;
+            }
+        };
+
+        let ino = match self.disk.find_inode_name(parent, name) {
+            Ok(Some(inode)) if inode.attributes.kind == FileType::Directory => {
+                operation.fail(reply, QrfsError::IsDirectory);
+                return ;
+            },
+            Ok(Some(inode)) => inode.attributes.ino,
+            Ok(None) => {
+                operation.fail(reply, QrfsError::NotFound);
+                return ;
+            },
+            Err(error) => {
+                operation.fail(reply, error);
+                return ;
+            }
+        };
+
+        if let Err(error) = self.check_removal(req, parent, ino) {
+            operation.fail(reply, error);
+            return ;
+        }
+
+        if let Err(error) = self.disk.remove_entry(parent, name) {
+            operation.fail(reply, error);
+            return ;
+        }
+
+        self.disk.decrease_nlink(ino);
+        self.remove_if_orphan(ino);
+        reply.ok();
+    }
+
+    /*
+        Función link del filesystem
+        
+        sirve para crear un hard link, un nombre nuevo para un inode existente
+    */
+    fn link(&mut self, req: &Request, ino: u64, newparent: u64, newname: &OsStr, reply: ReplyEntry) {
+        let operation = Operation::start("link").field("ino", ino).field("parent", newparent).name("name", newname);
+
+        match self.disk.get_inode(ino) {
+            Some(inode) if inode.attributes.kind == FileType::Directory => {
+                operation.fail(reply, QrfsError::NotPermitted);
+                return ;
+            },
+            Some(_) => (),
+            None => {
+                operation.fail(reply, QrfsError::NotFound);
+                return ;
+            }
+        }
+
+        if let Err(error) = self.check_permission(req, newparent, W_OK | X_OK) {
+            operation.fail(reply, error);
+            return ;
+        }
+
+        let newname = match entry_name(newname) {
+            Ok(newname) => newname,
+            Err(error) => {
+                operation.fail(reply, error);
+                return ;
+            }
+        };
+        if let Err(error) = self.disk.add_entry(newparent, newname, ino) {
+            operation.fail(reply, error);
+            return ;
+        }
+
+        self.disk.increase_nlink(ino);
+
+        match self.disk.get_inode(ino) {
+            Some(inode) => reply.entry(&get_time(), &inode.attributes, 0),
+            None => operation.fail(reply, QrfsError::NotFound)
+        }
+    }
+
+    /*
+        Función rmdir del filesystem
+        
+        sirve para eliminar una carpeta vacía del fs y liberar su inode
+    */
+    fn rmdir(&mut self, req: &Request, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        let operation = Operation::start("rmdir").field("parent", parent).name("name", name);
+
+        let name = match entry_name(name) {
+            Ok(name) => name,
+            Err(error) => {
+                operation.fail(reply, error);
+                return ;
+            }
+        };
+
+        let inode = self.disk.find_inode_name(parent, name);
+        
+        match inode {
+            Ok(Some(inode)) if inode.attributes.kind != FileType::Directory => operation.fail(reply, QrfsError::NotDirectory),
+            Ok(Some(inode)) if name == "." || name == ".." || inode.attributes.ino == 1 => operation.fail(reply, QrfsError::InvalidArgument),
+            Ok(Some(inode)) => {
+                let ino = inode.attributes.ino;
+                match self.disk.is_empty_directory(ino) {
+                    Ok(true) => (),
+                    Ok(false) => {
+                        operation.fail(reply, QrfsError::NotEmpty);
+                        return ;
+                    },
+                    Err(error) => {
+                        operation.fail(reply, error);
+                        return ;
+                    }
+                }
+
+                if let Err(error) = self.check_removal(req, parent, ino) {
+                    operation.fail(reply, error);
+                    return ;
+                }
+
+                if let Err(error) = self.disk.remove_entry(parent, name) {
+                    operation.fail(reply, error);
+                    return ;
+                }
+
+                self.disk.decrease_nlink(parent);
+                self.disk.remove_inode(ino);
+
+                reply.ok();
+            },
+            Ok(None) => operation.fail(reply, QrfsError::NotFound),
+            Err(error) => operation.fail(reply, error)
+        }
+    }
+
+    /*
+        Función statfs del filesystem
+        
+        sirve para desplegar las estadísticas del fs
+    */
+    fn statfs(&mut self, _req: &Request, _ino: u64, reply: fuse::ReplyStatfs) {
+        let _operation = Operation::start("statfs");
+
+        let blocks = self.disk.memory_blocks.len();
+        let bfree = self.disk.block_bitmap.free;
+        let bavail = bfree;
+        let bsize = self.disk.block_size;
+        let files = self.disk.max_files;
+        let namelen = NAMELEN;
+        let ffree = self.disk.inode_bitmap.free + (self.disk.max_files - self.disk.super_block.len());
+        let frsize = bsize as u32;
+
+        reply.statfs(
+            blocks as u64, 
+            bfree as u64, 
+            bavail as u64, 
+            files as u64, 
+            ffree as u64, 
+            bsize as u32, 
+            namelen, 
+            frsize);
+    }
+
+    /*
+        Función rename del filesystem
+        
+        sirve para renombrar o mover un archivo del fs,
+        fuse no envía flags de rename así que se usa el comportamiento por defecto
+    */
+    fn rename(&mut self, req: &Request, parent: u64, name: &OsStr, newparent: u64, newname: &OsStr, reply: ReplyEmpty) {
+        let operation = Operation::start("rename").field("parent", parent).name("name", name).field("newparent", newparent).name("newname", newname);
+
+        let name = match entry_name(name) {
+            Ok(name) => name,
+            Err(error) => {
+                operation.fail(reply, error);
+                return ;
+            }
+        };
+        let newname = match entry_name(newname) {
+            Ok(newname) => newname,
+            Err(error) => {
+                operation.fail(reply, error);
+                return ;
+            }
+        };
+
+        if let Err(error) = self.check_rename(req, parent, name, newparent, newname) {
+            operation.fail(reply, error);
+            return ;
+        }
+
+        match self.rename_entry(parent, name, newparent, newname, 0) {
+            Ok(()) => reply.ok(),
+            Err(error) => operation.fail(reply, error)
+        }
+    }
+
+    /*
+        Función setxattr del filesystem
+        
+        sirve para guardar un atributo extendido de un archivo del fs
+    */
+    fn setxattr(&mut self, req: &Request, ino: u64, name: &OsStr, value: &[u8], flags: u32, _position: u32, reply: ReplyEmpty) {
+        let operation = Operation::start("setxattr").field("ino", ino).name("name", name).field("size", value.len());
+
+        let name = match entry_name(name) {
+            Ok(name) => name,
+            Err(error) => {
+                operation.fail(reply, error);
+                return ;
+            }
+        };
+        if let Err(error) = self.check_xattr(req, ino, name, W_OK) {
+            operation.fail(reply, error);
+            return ;
+        }
+
+        match self.disk.set_xattr(ino, name, value, flags as c_int) {
+            Ok(()) => reply.ok(),
+            Err(error) => operation.fail(reply, error)
+        }
+    }
+
+    /*
+        Función getxattr del filesystem
+        
+        sirve para leer un atributo extendido, si size es 0 solo se responde el tamaño
+    */
+    fn getxattr(&mut self, req: &Request, ino: u64, name: &OsStr, size: u32, reply: ReplyXattr) {
+        let operation = Operation::start("getxattr").field("ino", ino).name("name", name).field("size", size);
+
+        let name = match entry_name(name) {
+            Ok(name) => name,
+            Err(error) => {
+                operation.fail(reply, error);
+                return ;
+            }
+        };
+        if let Err(error) = self.check_xattr(req, ino, name, R_OK) {
+            let error = if error == QrfsError::NotSupported { QrfsError::NoAttribute } else { error };
+            operation.fail(reply, error);
+            return ;
+        }
+
+        match self.disk.get_xattr(ino, name) {
+            Ok(value) if size == 0 => reply.size(value.len() as u32),
+            Ok(value) if value.len() > size as usize => operation.fail(reply, QrfsError::BufferTooSmall),
+            Ok(value) => reply.data(value),
+            Err(error) => operation.fail(reply, error)
+        }
+    }
+
+    /*
+        Función listxattr del filesystem
+        
+        sirve para listar los nombres de los atributos extendidos de un archivo del fs
+    */
+    fn listxattr(&mut self, _req: &Request, ino: u64, size: u32, reply: ReplyXattr) {
+        let operation = Operation::start("listxattr").field("ino", ino).field("size", size);
+
+        match self.disk.list_xattrs(ino) {
+            Ok(names) if size == 0 => reply.size(names.len() as u32),
+            Ok(names) if names.len() > size as usize => operation.fail(reply, QrfsError::BufferTooSmall),
+            Ok(names) => reply.data(&names),
+            Err(error) => operation.fail(reply, error)
+        }
+    }
+
+    /*
+        Función removexattr del filesystem
+        
+        sirve para eliminar un atributo extendido de un archivo del fs
+    */
+    fn removexattr(&mut self, req: &Request, ino: u64, name: &OsStr, reply: ReplyEmpty) {
+        let operation = Operation::start("removexattr").field("ino", ino).name("name", name);
+
+        let name = match entry_name(name) {
+            Ok(name) => name,
+            Err(error) => {
+                operation.fail(reply, error);
+                return ;
+            }
+        };
+        if let Err(error) = self.check_xattr(req, ino, name, W_OK) {
+            operation.fail(reply, error);
+            return ;
+        }
+
+        match self.disk.remove_xattr(ino, name) {
+            Ok(()) => reply.ok(),
+            Err(error) => operation.fail(reply, error)
+        }
+    }
+
+    /*
+        Función access del filesystem
+        
+        sirve para checar por los permisos de un archivo de fs
+    */
+    fn access(&mut self, req: &Request, ino: u64, mask: u32, reply: ReplyEmpty) {
+        let operation = Operation::start("access").field("ino", ino);
+
+        match self.check_permission(req, ino, mask as c_int & (R_OK | W_OK | X_OK)) {
+            Ok(()) => reply.ok(),
+            Err(error) => operation.fail(reply, error)
+        }
+    }
+
+    /*
+        Función fsync del filesystem
+        
+        sirve para guardar de inmediato un archivo del fs y sus memory blocks
+    */
+    fn fsync(&mut self, _req: &Request, ino: u64, _fh: u64, _datasync: bool, reply: ReplyEmpty) {
+        let operation = Operation::start("fsync").field("ino", ino);
+
+        match self.save_changes() {
+            Ok(()) => reply.ok(),
+            Err(error) => operation.fail(reply, error)
+        }
+    }
+
+    /*
+        Función fsyncdir del filesystem
+        
+        sirve para sincronizar las entradas de una carpeta del fs
+    */
+    fn fsyncdir(&mut self, _req: &Request, ino: u64, _fh: u64, _datasync: bool, reply: ReplyEmpty) {
+        let operation = Operation::start("fsyncdir").field("ino", ino);
+
+        match self.save_changes() {
+            Ok(()) => reply.ok(),
+            Err(error) => operation.fail(reply, error)
+        }
+    }
+
+    /*
+        Función opendir del filesystem
+        
+        sirve para abrir una carpeta del fs
+    */
+    fn opendir(&mut self, req: &Request, ino: u64, flags: u32, reply: ReplyOpen) {
+        let operation = Operation::start("opendir").field("ino", ino);
+
+        match self.check_permission(req, ino, R_OK) {
+            Ok(()) => reply.opened(ino, flags),
+            Err(error) => operation.fail(reply, error)
+        }
+    }
+}
+
+/*
+    Estructura que comparte el filesystem entre el hilo de fuse y el hilo principal,
+    el hilo principal la usa para el autosave aunque no lleguen operaciones
+*/
+struct QrfsSession {
+    fs: Arc<Mutex<QRFS>>
+}
+
+/*
+    Función que bloquea el filesystem compartido, si otro hilo falló con el candado tomado
+    se sigue usando el filesystem para no perder el guardado al desmontar
+    E: fs (filesystem compartido)
+    S: el filesystem bloqueado
+*/
+fn lock_fs(fs: &Mutex<QRFS>) -> MutexGuard<'_, QRFS> {
+    fs.lock().unwrap_or_else(|error| error.into_inner())
+}
+
+//Cada operación de fuse se pasa al QRFS compartido
+impl Filesystem for QrfsSession {
+    fn lookup(&mut self, req: &Request, parent: u64, name: &std::ffi::OsStr, reply: ReplyEntry) {
+        lock_fs(&self.fs).lookup(req, parent, name, reply)
+    }
+
+    fn getattr(&mut self, _req: &Request, ino: u64, reply: ReplyAttr) {
+        lock_fs(&self.fs).getattr(_req, ino, reply)
+    }
+
+    fn setattr(&mut self, req: &Request, ino: u64, mode: Option<u32>, uid: Option<u32>, gid: Option<u32>, size: Option<u64>, atime: Option<Timespec>, mtime: Option<Timespec>, _fh: Option<u64>, _crtime: Option<Timespec>, _chgtime: Option<Timespec>, _bkuptime: Option<Timespec>, _flags: Option<u32>, reply: ReplyAttr) {
+        lock_fs(&self.fs).setattr(req, ino, mode, uid, gid, size, atime, mtime, _fh, _crtime, _chgtime, _bkuptime, _flags, reply)
+    }
+
+    fn read(&mut self, _req: &Request, ino: u64, _fh: u64, offset: i64, size: u32, reply: ReplyData) {
+        lock_fs(&self.fs).read(_req, ino, _fh, offset, size, reply)
+    }
+
+    fn readdir(&mut self, _req: &Request, ino: u64, _fh: u64, offset: i64, reply: ReplyDirectory) {
+        lock_fs(&self.fs).readdir(_req, ino, _fh, offset, reply)
+    }
+
+    fn mkdir(&mut self, req: &Request, parent: u64, name: &OsStr, mode: u32, reply: ReplyEntry) {
+        lock_fs(&self.fs).mkdir(req, parent, name, mode, reply)
+    }
+
+    fn create(&mut self, req: &Request, parent: u64, name: &OsStr, mode: u32, flags: u32, reply: ReplyCreate) {
+        lock_fs(&self.fs).create(req, parent, name, mode, flags, reply)
+    }
+
+    fn symlink(&mut self, req: &Request, parent: u64, name: &OsStr, link: &Path, reply: ReplyEntry) {
+        lock_fs(&self.fs).symlink(req, parent, name, link, reply)
+    }
+
+    fn readlink(&mut self, _req: &Request, ino: u64, reply: ReplyData) {
+        lock_fs(&self.fs).readlink(_req, ino, reply)
+    }
+
+    fn open(&mut self, req: &Request, ino: u64, flags: u32, reply: ReplyOpen) {
+        lock_fs(&self.fs).open(req, ino, flags, reply)
+    }
+
+    fn release(&mut self, _req: &Request, ino: u64, _fh: u64, flags: u32, _lock_owner: u64, _flush: bool, reply: ReplyEmpty) {
+        lock_fs(&self.fs).release(_req, ino, _fh, flags, _lock_owner, _flush, reply)
+    }
+
+    fn flush(&mut self, _req: &Request, ino: u64, _fh: u64, _lock_owner: u64, reply: ReplyEmpty) {
+        lock_fs(&self.fs).flush(_req, ino, _fh, _lock_owner, reply)
+    }
+
+    fn write(&mut self, _req: &Request, ino: u64, _fh: u64, offset: i64, data: &[u8], _flags: u32, reply: ReplyWrite) {
+        lock_fs(&self.fs).write(_req, ino, _fh, offset, data, _flags, reply)
+    }
+
+    fn unlink(&mut self, req: &Request, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        lock_fs(&self.fs).unlink(req, parent, name, reply)
+    }
+
+    fn link(&mut self, req: &Request, ino: u64, newparent: u64, newname: &OsStr, reply: ReplyEntry) {
+        lock_fs(&self.fs).link(req, ino, newparent, newname, reply)
+    }
+
+    fn rmdir(&mut self, req: &Request, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        lock_fs(&self.fs).rmdir(req, parent, name, reply)
+    }
+
+    fn statfs(&mut self, _req: &Request, _ino: u64, reply: fuse::ReplyStatfs) {
+        lock_fs(&self.fs).statfs(_req, _ino, reply)
+    }
+
+    fn rename(&mut self, req: &Request, parent: u64, name: &OsStr, newparent: u64, newname: &OsStr, reply: ReplyEmpty) {
+        lock_fs(&self.fs).rename(req, parent, name, newparent, newname, reply)
+    }
+
+    fn setxattr(&mut self, req: &Request, ino: u64, name: &OsStr, value: &[u8], flags: u32, _position: u32, reply: ReplyEmpty) {
+        lock_fs(&self.fs).setxattr(req, ino, name, value, flags, _position, reply)
+    }
+
+    fn getxattr(&mut self, req: &Request, ino: u64, name: &OsStr, size: u32, reply: ReplyXattr) {
+        lock_fs(&self.fs).getxattr(req, ino, name, size, reply)
+    }
+
+    fn listxattr(&mut self, _req: &Request, ino: u64, size: u32, reply: ReplyXattr) {
+        lock_fs(&self.fs).listxattr(_req, ino, size, reply)
+    }
+
+    fn removexattr(&mut self, req: &Request, ino: u64, name: &OsStr, reply: ReplyEmpty) {
+        lock_fs(&self.fs).removexattr(req, ino, name, reply)
+    }
+
+    fn access(&mut self, req: &Request, ino: u64, mask: u32, reply: ReplyEmpty) {
+        lock_fs(&self.fs).access(req, ino, mask, reply)
+    }
+
+    fn fsync(&mut self, _req: &Request, ino: u64, _fh: u64, _datasync: bool, reply: ReplyEmpty) {
+        lock_fs(&self.fs).fsync(_req, ino, _fh, _datasync, reply)
+    }
+
+    fn fsyncdir(&mut self, _req: &Request, ino: u64, _fh: u64, _datasync: bool, reply: ReplyEmpty) {
+        lock_fs(&self.fs).fsyncdir(_req, ino, _fh, _datasync, reply)
+    }
+
+    fn opendir(&mut self, req: &Request, ino: u64, flags: u32, reply: ReplyOpen) {
+        lock_fs(&self.fs).opendir(req, ino, flags, reply)
+    }
+}
+
+fn main() {
+    let config = match Config::from_args(env::args().skip(1).collect()) {
+        Ok(config) => config,
+        Err(error) => {
+            println!("Error: {}", error);
+            println!("Uso: qrfs <mountpoint> [--load <carpeta>] [--save-on-exit <carpeta>] [--qr-dir <carpeta>] [--autosave <segundos>] [--log-level <nivel>] [--log-file <archivo>] [--no-save] [--interactive]");
+            return ;
+        }
+    };
+
+    if let Err(error) = init_logger(&config) {
+        println!("Error: {}", error);
+        return ;
+    }
+
+    let mountpoint = config.mountpoint.clone();
+    let fs = Arc::new(Mutex::new(QRFS::new(config)));
+    let session_fs = QrfsSession { fs: Arc::clone(&fs) };
+
+    let options = ["-o", "nonempty"]
+        .iter()
+        .map(|o| o.as_ref())
+        .collect::<Vec<&OsStr>>();
+
+    //Las señales se bloquean antes de crear el hilo de fuse para que solo el hilo principal las reciba
+    let signals = block_signals();
+    let session_mountpoint = mountpoint.clone();
+    
+    log(LogLevel::Info, &format!("QRFS iniciado en {}", mountpoint));
+    
+    let session = thread::spawn(move || {
+        if let Err(error) = fuse::mount(session_fs, &session_mountpoint, &options) {
+            log(LogLevel::Error, &format!("no se pudo montar {}: {}", session_mountpoint, error));
+        }
+    });
+
+    //Se sigue esperando hasta que fuse termine, si el desmontaje falla cada señal nueva lo reintenta
+    while let Some(signal) = wait_signal(&signals, &session, &fs) {
+        log(LogLevel::Info, &format!("señal {} recibida, desmontando {}", signal, mountpoint));
+        TERMINATING.store(true, Ordering::SeqCst);
+
+        if !unmount(&mountpoint) {
+            log(LogLevel::Error, &format!("no se pudo desmontar {}, envíe otra señal para reintentar", mountpoint));
+        }
+    }
+
+    if session.join().is_err() {
+        log(LogLevel::Error, "el hilo de fuse terminó de forma inesperada");
+    }
+
+    //El QRFS se libera aquí, después de que fuse termina, y al liberarlo se guarda según la configuración
+    drop(fs);
+}
+
+/*
+    Función que bloquea SIGINT, SIGTERM y SIGHUP para recibirlos con sigtimedwait
+    E: N/A
+    S: el conjunto de señales bloqueadas
+*/
+fn block_signals() -> sigset_t {
+    unsafe {
+        let mut signals: sigset_t = mem::zeroed();
+        sigemptyset(&mut signals);
+        sigaddset(&mut signals, SIGINT);
+        sigaddset(&mut signals, SIGTERM);
+        sigaddset(&mut signals, SIGHUP);
+        pthread_sigmask(SIG_BLOCK, &signals, ptr::null_mut());
+        signals
+    }
+}
+
+/*
+    Función que espera una señal mientras el filesystem siga montado,
+    cada segundo sin señales revisa si toca el autosave
+    E: signals (señales bloqueadas), session (hilo de fuse) y fs (filesystem compartido)
+    S: un opcional con la señal recibida, None si el filesystem se desmontó por otro medio
+*/
+fn wait_signal(signals: &sigset_t, session: &thread::JoinHandle<()>, fs: &Mutex<QRFS>) -> Option<c_int> {
+    let timeout = timespec { tv_sec: 1, tv_nsec: 0 };
+
+    while !session.is_finished() {
+        let signal = unsafe { sigtimedwait(signals, ptr::null_mut(), &timeout) };
+        if signal > 0 {
+            return Some(signal);
+        }
+
+        lock_fs(fs).check_autosave();
+    }
+
+    None
+}
+
+/*
+    Función que desmonta el filesystem con fusermount, si está ocupado se hace un desmontaje lazy
+    y si fusermount no está disponible se intenta con umount2 directamente
+    E: mountpoint (carpeta donde está montado)
+    S: true si se pudo desmontar
+*/
+fn unmount(mountpoint: &str) -> bool {
+    let unmounted = ["-u", "-uz"].iter().any(|flags| {
+        Command::new("fusermount")
+            .arg(flags)
+            .arg(mountpoint)
+            .status()
+            .is_ok_and(|status| status.success())
+    });
+
+    if unmounted {
+        return true;
+    }
+
+    match CString::new(mountpoint) {
+        Ok(path) => unsafe { umount2(path.as_ptr(), MNT_DETACH) == 0 },
+        Err(_) => false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn disk() -> Disk {
+        Disk::new(String::from("/"), 1 << 22, 1024)
+    }
+
+    fn attributes(perm: u16, uid: u32, gid: u32, kind: FileType) -> FileAttr {
+        let ttl = get_time();
+        FileAttr {
+            ino: 0,
+            size: 0,
+            blocks: 0,
+            atime: ttl,
+            mtime: ttl,
+            ctime: ttl,
+            crtime: ttl,
+            kind,
+            perm,
+            nlink: 1,
+            uid,
+            gid,
+            rdev: 0,
+            flags: 0
+        }
+    }
+
+    //Crea un inode y su entrada en parent igual que create y mkdir
+    fn new_inode(disk: &mut Disk, parent: u64, name: &str, kind: FileType) -> u64 {
+        let ino = disk.allocate_ino().unwrap();
+        let mut attributes = attributes(0o755, 1000, 100, kind);
+        attributes.ino = ino;
+
+        disk.write_inode(Inode {
+            attributes,
+            references: vec![None; FILE_REFERENCES],
+            link_target: None,
+            xattrs: BTreeMap::new()
+        }).unwrap();
+
+        if kind == FileType::Directory {
+            disk.init_directory(ino, parent).unwrap();
+            disk.increase_nlink(ino);
+            disk.increase_nlink(parent);
+        }
+
+        disk.add_entry(parent, name, ino).unwrap();
+        ino
+    }
+
+    fn qrfs() -> QRFS {
+        QRFS {
+            disk: disk(),
+            config: Config::from_args(args(&["/mnt", "--no-save"])).unwrap(),
+            open_files: HashMap::new(),
+            last_save: Instant::now(),
+            base_saved: false
+        }
+    }
+
+    #[test]
+    fn join_chunks_out_of_order() {
+        let disk = Disk::new(String::from("/"), 1 << 20, 1024);
+        let object: Vec<u8> = (0..3 * QR_CHUNK_SIZE + 100).map(|byte| byte as u8).collect();
+
+        let mut chunks = disk.split_object(7, &object);
+        assert_eq!(chunks.len(), 4);
+        chunks.reverse();
+        chunks.swap(0, 2);
+
+        assert_eq!(disk.join_chunks(7, chunks), Some(object));
+    }
+
+    #[test]
+    fn join_chunks_empty_object() {
+        let disk = Disk::new(String::from("/"), 1 << 20, 1024);
+
+        let chunks = disk.split_object(1, &[]);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(disk.join_chunks(1, chunks), Some(Vec::new()));
+    }
+
+    #[test]
+    fn join_chunks_missing_chunk() {
+        let disk = Disk::new(String::from("/"), 1 << 20, 1024);
+        let object = vec![1u8; 2 * QR_CHUNK_SIZE + 1];
+
+        let mut chunks = disk.split_object(3, &object);
+        chunks.remove(1);
+
+        assert_eq!(disk.join_chunks(3, chunks), None);
+    }
+
+    #[test]
+    fn join_chunks_checksum_mismatch() {
+        let disk = Disk::new(String::from("/"), 1 << 20, 1024);
+        let object = vec![1u8; QR_CHUNK_SIZE + 1];
+
+        let mut chunks = disk.split_object(3, &object);
+        chunks[1].data[0] = 2;
+
+        assert_eq!(disk.join_chunks(3, chunks), None);
+    }
+
+    #[test]
+    fn checksum_known_value() {
+        assert_eq!(checksum(b""), 1);
+        assert_eq!(checksum(b"Wikipedia"), 0x11e60398);
+    }
+
+    #[test]
+    fn config_from_args() {
+        let config = Config::from_args(args(&["/mnt", "--load", "entrada", "--save-on-exit", "qr", "--no-save", "--interactive"])).unwrap();
+
+        assert_eq!(config.mountpoint, "/mnt");
+        assert_eq!(config.load_path, Some(PathBuf::from("entrada")));
+        assert_eq!(config.save_path, Some(PathBuf::from("qr")));
+        assert!(config.no_save);
+        assert!(config.interactive);
+        assert_eq!(config.sync_path(), None);
+    }
+
+    #[test]
+    fn config_from_args_errors() {
+        assert!(Config::from_args(args(&[])).is_err());
+        assert!(Config::from_args(args(&["/mnt", "/otro"])).is_err());
+        assert!(Config::from_args(args(&["/mnt", "--load"])).is_err());
+        assert!(Config::from_args(args(&["/mnt", "--save-on-exit"])).is_err());
+        assert!(Config::from_args(args(&["/mnt", "--desconocida"])).is_err());
+    }
+
+    #[test]
+    fn write_and_read_at_offsets() {
+        let mut disk = disk();
+        let ino = new_inode(&mut disk, 1, "archivo", FileType::RegularFile);
+
+        assert_eq!(disk.write_file(ino, 0, b"hola mundo"), Ok(10));
+        assert_eq!(disk.write_file(ino, 5, b"MUNDO!"), Ok(6));
+        assert_eq!(disk.read_file(ino, 0, 100), Ok(b"hola MUNDO!".to_vec()));
+        assert_eq!(disk.read_file(ino, 5, 3), Ok(b"MUN".to_vec()));
+        assert_eq!(disk.read_file(ino, 11, 10), Ok(Vec::new()));
+        assert_eq!(disk.get_inode(ino).unwrap().attributes.size, 11);
+    }
+
+    #[test]
+    fn write_after_end_fills_with_zeros() {
+        let mut disk = disk();
+        let ino = new_inode(&mut disk, 1, "archivo", FileType::RegularFile);
+
+        disk.write_file(ino, 0, b"ab").unwrap();
+        disk.write_file(ino, 2500, b"cd").unwrap();
+
+        let content = disk.read_file(ino, 0, 4000).unwrap();
+        assert_eq!(content.len(), 2502);
+        assert_eq!(&content[..2], b"ab");
+        assert!(content[2..2500].iter().all(|byte| *byte == 0));
+        assert_eq!(&content[2500..], b"cd");
+        assert_eq!(disk.get_file_block(ino, 1), Ok(None));
+    }
+
+    #[test]
+    fn write_across_indirect_blocks() {
+        let mut disk = disk();
+        let ino = new_inode(&mut disk, 1, "archivo", FileType::RegularFile);
+        let data: Vec<u8> = (0..3 * 1024).map(|byte| (byte % 251) as u8).collect();
+
+        //Del último bloque directo al primero del indirecto simple
+        let offset = (DIRECT_BLOCKS - 1) * 1024 + 512;
+        assert_eq!(disk.write_file(ino, offset, &data), Ok(data.len()));
+        assert_eq!(disk.read_file(ino, offset, data.len()), Ok(data.clone()));
+        assert!(disk.get_inode(ino).unwrap().references[SINGLE_INDIRECT].is_some());
+
+        //Primer bloque del indirecto doble
+        let index = DIRECT_BLOCKS + disk.pointers_per_block();
+        disk.write_file(ino, index * 1024 + 10, b"doble").unwrap();
+        assert_eq!(disk.read_file(ino, index * 1024 + 10, 5), Ok(b"doble".to_vec()));
+        assert!(disk.get_inode(ino).unwrap().references[DOUBLE_INDIRECT].is_some());
+    }
+
+    #[test]
+    fn blocks_follow_allocations() {
+        let mut disk = disk();
+        let ino = new_inode(&mut disk, 1, "archivo", FileType::RegularFile);
+
+        disk.write_file(ino, 0, &[1u8; 3 * 1024]).unwrap();
+        assert_eq!(disk.get_inode(ino).unwrap().attributes.blocks, 3 * 2);
+
+        //Un bloque de datos más el bloque indirecto simple
+        disk.write_file(ino, DIRECT_BLOCKS * 1024, b"x").unwrap();
+        assert_eq!(disk.get_inode(ino).unwrap().attributes.blocks, 5 * 2);
+
+        disk.truncate_file(ino, 1024).unwrap();
+        assert_eq!(disk.get_inode(ino).unwrap().attributes.blocks, 2);
+
+        disk.free_file_blocks(ino);
+        assert_eq!(disk.get_inode(ino).unwrap().attributes.blocks, 0);
+    }
+
+    #[test]
+    fn bitmap_allocate_and_clear() {
+        let mut bitmap = Bitmap::new(70);
+
+        for position in 0..70 {
+            assert_eq!(bitmap.allocate(), Some(position));
+        }
+        assert_eq!(bitmap.allocate(), None);
+        assert_eq!(bitmap.free, 0);
+
+        bitmap.clear(66);
+        bitmap.clear(5);
+        assert_eq!(bitmap.free, 2);
+        assert_eq!(bitmap.allocate(), Some(5));
+        assert_eq!(bitmap.allocate(), Some(66));
+        assert_eq!(bitmap.allocate(), None);
+    }
+
+    #[test]
+    fn bitmap_allocate_wraps_around() {
+        let mut bitmap = Bitmap::new(200);
+
+        for position in 64..200 {
+            bitmap.set(position);
+        }
+        bitmap.next = 2;
+
+        assert_eq!(bitmap.allocate(), Some(0));
+        assert_eq!(bitmap.next, 0);
+    }
+
+    #[test]
+    fn freed_blocks_return_to_the_bitmap() {
+        let mut disk = disk();
+        let free = disk.block_bitmap.free;
+        let ino = new_inode(&mut disk, 1, "archivo", FileType::RegularFile);
+
+        disk.write_file(ino, 0, &[1u8; 4 * 1024]).unwrap();
+        assert_eq!(disk.block_bitmap.free, free - 4);
+
+        disk.remove_inode(ino);
+        assert_eq!(disk.block_bitmap.free, free);
+    }
+
+    #[test]
+    fn unlink_while_open_frees_on_close() {
+        let mut fs = qrfs();
+        let free = fs.disk.block_bitmap.free;
+        let ino = new_inode(&mut fs.disk, 1, "archivo", FileType::RegularFile);
+        fs.disk.write_file(ino, 0, &[1u8; 2048]).unwrap();
+
+        fs.open_file(ino);
+        fs.disk.remove_entry(1, "archivo").unwrap();
+        fs.disk.decrease_nlink(ino);
+        fs.remove_if_orphan(ino);
+        assert!(fs.disk.get_inode(ino).is_some());
+        assert_eq!(fs.disk.read_file(ino, 0, 4), Ok(vec![1u8; 4]));
+
+        fs.close_file(ino);
+        assert!(fs.disk.get_inode(ino).is_none());
+        assert!(!fs.disk.inode_bitmap.is_set(ino as usize - 1));
+        assert_eq!(fs.disk.block_bitmap.free, free);
+    }
+
+    #[test]
+    fn load_frees_saved_orphans() {
+        let mut disk = disk();
+        let orphan = new_inode(&mut disk, 1, "huerfano", FileType::RegularFile);
+        let kept = new_inode(&mut disk, 1, "archivo", FileType::RegularFile);
+        disk.write_file(orphan, 0, b"datos").unwrap();
+        disk.remove_entry(1, "huerfano").unwrap();
+        disk.decrease_nlink(orphan);
+
+        disk.remove_orphans();
+
+        assert!(disk.get_inode(orphan).is_none());
+        assert!(disk.get_inode(kept).is_some());
+        assert!(disk.get_inode(1).is_some());
+    }
+
+    #[test]
+    fn rmdir_requires_empty_and_frees_directory() {
+        let mut disk = disk();
+        let inodes = disk.inode_bitmap.free;
+        let blocks = disk.block_bitmap.free;
+        let directory = new_inode(&mut disk, 1, "carpeta", FileType::Directory);
+        let file = new_inode(&mut disk, directory, "archivo", FileType::RegularFile);
+        assert_eq!(disk.get_inode(1).unwrap().attributes.nlink, 3);
+
+        assert_eq!(disk.is_empty_directory(directory), Ok(false));
+        disk.remove_entry(directory, "archivo").unwrap();
+        disk.remove_inode(file);
+        assert_eq!(disk.is_empty_directory(directory), Ok(true));
+
+        disk.remove_entry(1, "carpeta").unwrap();
+        disk.decrease_nlink(1);
+        disk.remove_inode(directory);
+
+        assert_eq!(disk.find_entry(1, "carpeta"), Ok(None));
+        assert_eq!(disk.get_inode(1).unwrap().attributes.nlink, 2);
+        assert_eq!(disk.inode_bitmap.free, inodes);
+        assert_eq!(disk.block_bitmap.free, blocks);
+    }
+
+    #[test]
+    fn rename_replaces_existing_file() {
+        let mut fs = qrfs();
+        let source = new_inode(&mut fs.disk, 1, "a", FileType::RegularFile);
+        let target = new_inode(&mut fs.disk, 1, "b", FileType::RegularFile);
+        fs.disk.write_file(target, 0, b"viejo").unwrap();
+
+        fs.rename_entry(1, "a", 1, "b", 0).unwrap();
+
+        assert_eq!(fs.disk.find_entry(1, "a"), Ok(None));
+        assert_eq!(fs.disk.find_entry(1, "b"), Ok(Some(source)));
+        assert!(fs.disk.get_inode(target).is_none());
+    }
+
+    #[test]
+    fn rename_moves_directory_between_parents() {
+        let mut fs = qrfs();
+        let first = new_inode(&mut fs.disk, 1, "primera", FileType::Directory);
+        let second = new_inode(&mut fs.disk, 1, "segunda", FileType::Directory);
+        let moved = new_inode(&mut fs.disk, first, "movida", FileType::Directory);
+
+        fs.rename_entry(first, "movida", second, "movida", 0).unwrap();
+
+        assert_eq!(fs.disk.find_entry(second, "movida"), Ok(Some(moved)));
+        assert_eq!(fs.disk.find_entry(moved, ".."), Ok(Some(second)));
+        assert_eq!(fs.disk.get_inode(first).unwrap().attributes.nlink, 2);
+        assert_eq!(fs.disk.get_inode(second).unwrap().attributes.nlink, 3);
+    }
+
+    #[test]
+    fn rename_rejects_invalid_targets() {
+        let mut fs = qrfs();
+        let directory = new_inode(&mut fs.disk, 1, "carpeta", FileType::Directory);
+        new_inode(&mut fs.disk, directory, "interna", FileType::Directory);
+        let full = new_inode(&mut fs.disk, 1, "llena", FileType::Directory);
+        new_inode(&mut fs.disk, full, "archivo", FileType::RegularFile);
+        new_inode(&mut fs.disk, 1, "archivo", FileType::RegularFile);
+
+        assert_eq!(fs.rename_entry(1, "carpeta", directory, "dentro", 0), Err(QrfsError::InvalidArgument));
+        assert_eq!(fs.rename_entry(1, "carpeta", 1, "llena", 0), Err(QrfsError::NotEmpty));
+        assert_eq!(fs.rename_entry(1, "carpeta", 1, "archivo", 0), Err(QrfsError::NotDirectory));
+        assert_eq!(fs.rename_entry(1, "archivo", 1, "carpeta", 0), Err(QrfsError::IsDirectory));
+        assert_eq!(fs.rename_entry(1, "archivo", 1, "llena", RENAME_NOREPLACE), Err(QrfsError::AlreadyExists));
+    }
+
+    #[test]
+    fn truncate_shrinks_and_zero_fills() {
+        let mut disk = disk();
+        let ino = new_inode(&mut disk, 1, "archivo", FileType::RegularFile);
+        disk.write_file(ino, 0, &[7u8; 3000]).unwrap();
+
+        disk.truncate_file(ino, 1500).unwrap();
+        assert_eq!(disk.get_file_block(ino, 2), Ok(None));
+        assert_eq!(disk.read_file(ino, 0, 5000).unwrap().len(), 1500);
+
+        //Los bytes cortados no reaparecen al volver a crecer
+        disk.truncate_file(ino, 2500).unwrap();
+        let content = disk.read_file(ino, 0, 5000).unwrap();
+        assert_eq!(content.len(), 2500);
+        assert!(content[..1500].iter().all(|byte| *byte == 7));
+        assert!(content[1500..].iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn truncate_limits_and_sparse_release() {
+        let mut disk = disk();
+        let blocks = disk.block_bitmap.free;
+        let ino = new_inode(&mut disk, 1, "archivo", FileType::RegularFile);
+        let max = disk.max_file_size();
+
+        assert_eq!(disk.truncate_file(ino, max + 1), Err(QrfsError::FileTooBig));
+        assert_eq!(disk.truncate_file(ino, 1 << 62), Err(QrfsError::FileTooBig));
+
+        disk.truncate_file(ino, max).unwrap();
+        disk.write_file(ino, max as usize - 1, b"x").unwrap();
+        disk.write_file(ino, 0, b"x").unwrap();
+
+        disk.truncate_file(ino, 0).unwrap();
+        assert_eq!(disk.block_bitmap.free, blocks);
+        assert_eq!(disk.get_inode(ino).unwrap().references, vec![None; FILE_REFERENCES]);
+    }
+
+    #[test]
+    fn symlink_targets_inline_and_in_blocks() {
+        let mut disk = disk();
+        let short = new_inode(&mut disk, 1, "corto", FileType::Symlink);
+        let long = new_inode(&mut disk, 1, "largo", FileType::Symlink);
+        let file = new_inode(&mut disk, 1, "archivo", FileType::RegularFile);
+        let long_target = vec![b'a'; 1500];
+
+        disk.write_link_target(short, b"destino").unwrap();
+        disk.write_link_target(long, &long_target).unwrap();
+
+        assert_eq!(disk.get_inode(short).unwrap().link_target, Some(b"destino".to_vec()));
+        assert_eq!(disk.get_file_block(short, 0), Ok(None));
+        assert_eq!(disk.read_link_target(short), Ok(b"destino".to_vec()));
+
+        assert_eq!(disk.get_inode(long).unwrap().link_target, None);
+        assert_eq!(disk.file_blocks(long).len(), 2);
+        assert_eq!(disk.read_link_target(long), Ok(long_target));
+        assert_eq!(disk.get_inode(long).unwrap().attributes.size, 1500);
+
+        assert_eq!(disk.read_link_target(file), Err(QrfsError::InvalidArgument));
+    }
+
+    #[test]
+    fn hard_links_share_the_inode() {
+        let mut fs = qrfs();
+        let ino = new_inode(&mut fs.disk, 1, "original", FileType::RegularFile);
+        let directory = new_inode(&mut fs.disk, 1, "carpeta", FileType::Directory);
+        fs.disk.write_file(ino, 0, b"compartido").unwrap();
+
+        fs.disk.add_entry(directory, "enlace", ino).unwrap();
+        fs.disk.increase_nlink(ino);
+        assert_eq!(fs.disk.get_inode(ino).unwrap().attributes.nlink, 2);
+        assert_eq!(fs.disk.find_entry(directory, "enlace"), Ok(Some(ino)));
+
+        fs.disk.remove_entry(1, "original").unwrap();
+        fs.disk.decrease_nlink(ino);
+        fs.remove_if_orphan(ino);
+        assert_eq!(fs.disk.get_inode(ino).unwrap().attributes.nlink, 1);
+        assert_eq!(fs.disk.read_file(ino, 0, 20), Ok(b"compartido".to_vec()));
+
+        fs.disk.remove_entry(directory, "enlace").unwrap();
+        fs.disk.decrease_nlink(ino);
+        fs.remove_if_orphan(ino);
+        assert!(fs.disk.get_inode(ino).is_none());
+    }
+
+    #[test]
+    fn open_mask_flags() {
+        assert_eq!(open_mask(O_RDONLY as u32), Ok(R_OK));
+        assert_eq!(open_mask(O_WRONLY as u32), Ok(W_OK));
+        assert_eq!(open_mask(O_RDWR as u32), Ok(R_OK | W_OK));
+        assert_eq!(open_mask((O_RDONLY | O_TRUNC) as u32), Ok(R_OK | W_OK));
+        assert_eq!(open_mask(O_ACCMODE as u32), Err(QrfsError::InvalidArgument));
+    }
+
+    #[test]
+    fn has_permission_classes() {
+        let file = attributes(0o640, 1000, 100, FileType::RegularFile);
+
+        assert!(has_permission(&file, 1000, 100, R_OK | W_OK));
+        assert!(!has_permission(&file, 1000, 100, X_OK));
+        assert!(has_permission(&file, 2000, 100, R_OK));
+        assert!(!has_permission(&file, 2000, 100, W_OK));
+        assert!(!has_permission(&file, 2000, 200, R_OK));
+
+        assert!(has_permission(&file, 0, 0, R_OK | W_OK));
+        assert!(!has_permission(&file, 0, 0, X_OK));
+
+        let directory = attributes(0o700, 1000, 100, FileType::Directory);
+        assert!(has_permission(&directory, 0, 0, X_OK));
+    }
+
+    #[test]
+    fn setgid_is_inherited_by_subdirectories() {
+        let mut fs = qrfs();
+        let directory = new_inode(&mut fs.disk, 1, "compartida", FileType::Directory);
+        assert!(!fs.has_setgid(directory));
+
+        fs.disk.get_inode_mutable(directory).unwrap().attributes.perm |= S_ISGID as u16;
+        assert!(fs.has_setgid(directory));
+        assert!(!fs.has_setgid(1));
+    }
+
+    #[test]
+    fn xattr_flags_and_limits() {
+        let mut disk = disk();
+        let ino = new_inode(&mut disk, 1, "archivo", FileType::RegularFile);
+
+        assert_eq!(disk.get_xattr(ino, "user.a"), Err(QrfsError::NoAttribute));
+        assert_eq!(disk.set_xattr(ino, "user.a", b"x", XATTR_REPLACE), Err(QrfsError::NoAttribute));
+        disk.set_xattr(ino, "user.a", b"hola", XATTR_CREATE).unwrap();
+        assert_eq!(disk.set_xattr(ino, "user.a", b"x", XATTR_CREATE), Err(QrfsError::AlreadyExists));
+        disk.set_xattr(ino, "user.a", b"chao", XATTR_REPLACE).unwrap();
+        disk.set_xattr(ino, "user.b", b"", 0).unwrap();
+
+        assert_eq!(disk.get_xattr(ino, "user.a"), Ok(&b"chao".to_vec()));
+        assert_eq!(disk.list_xattrs(ino), Ok(b"user.a\0user.b\0".to_vec()));
+
+        let long_name = format!("user.{}", "n".repeat(XATTR_NAME_MAX));
+        assert_eq!(disk.set_xattr(ino, &long_name, b"x", 0).unwrap_err().errno(), ERANGE);
+        assert_eq!(disk.set_xattr(ino, "user.c", &vec![0; XATTR_SIZE_MAX + 1], 0).unwrap_err().errno(), E2BIG);
+
+        disk.remove_xattr(ino, "user.a").unwrap();
+        assert_eq!(disk.remove_xattr(ino, "user.a"), Err(QrfsError::NoAttribute));
+        assert_eq!(disk.list_xattrs(ino), Ok(b"user.b\0".to_vec()));
+    }
+
+    #[test]
+    fn config_autosave_needs_save_path() {
+        let config = Config::from_args(args(&["/mnt", "--save-on-exit", "qr", "--autosave", "30"])).unwrap();
+        assert_eq!(config.autosave, Some(30));
+
+        assert!(Config::from_args(args(&["/mnt", "--autosave", "10"])).is_err());
+        assert!(Config::from_args(args(&["/mnt", "--save-on-exit", "qr", "--autosave", "0"])).is_err());
+        assert!(Config::from_args(args(&["/mnt", "--save-on-exit", "qr", "--autosave", "diez"])).is_err());
+        assert!(Config::from_args(args(&["/mnt", "--save-on-exit", "qr", "--autosave", "10", "--no-save"])).is_err());
+    }
+
+    #[test]
+    fn changes_are_tracked_until_cleared() {
+        let mut disk = disk();
+        disk.clear_changes();
+        assert!(!disk.has_changes());
+
+        let ino = new_inode(&mut disk, 1, "archivo", FileType::RegularFile);
+        disk.write_file(ino, 0, b"datos").unwrap();
+        assert!(disk.dirty_inodes.contains(&ino));
+        assert!(disk.dirty_inodes.contains(&1));
+        assert!(!disk.dirty_blocks.is_empty());
+
+        disk.clear_changes();
+        assert!(!disk.has_changes());
+    }
+
+    #[test]
+    fn bitmap_grow_keeps_allocated() {
+        let mut bitmap = Bitmap::new(64);
+
+        for _ in 0..64 {
+            bitmap.allocate();
+        }
+        bitmap.grow(100);
+
+        assert!(bitmap.is_set(63));
+        assert_eq!(bitmap.free, 36);
+        assert_eq!(bitmap.allocate(), Some(64));
+    }
+
+    #[test]
+    fn inode_table_grows_when_full() {
+        let mut disk = disk();
+        let initial = disk.super_block.len();
+
+        let inos: Vec<u64> = (0..initial * 3)
+            .map(|number| new_inode(&mut disk, 1, &format!("archivo{}", number), FileType::RegularFile))
+            .collect();
+
+        assert!(disk.super_block.len() > initial);
+        assert_eq!(disk.super_block.len(), disk.inode_bitmap.size);
+        assert_eq!(disk.find_entry(1, "archivo0"), Ok(Some(inos[0])));
+    }
+
+    #[test]
+    fn directory_grows_past_one_block() {
+        let mut disk = disk();
+        let directory = new_inode(&mut disk, 1, "carpeta", FileType::Directory);
+        let count = disk.entries_per_block() * 4;
+
+        for number in 0..count {
+            disk.add_entry(directory, &format!("entrada{}", number), 1).unwrap();
+        }
+
+        assert!(disk.directory_blocks(directory) > 4);
+        assert_eq!(disk.get_entries(directory).unwrap().iter().flatten().count(), count + 2);
+        for number in 0..count {
+            assert_eq!(disk.find_entry(directory, &format!("entrada{}", number)), Ok(Some(1)));
+        }
+    }
+
+    #[test]
+    fn lookup_compares_exact_names() {
+        let mut disk = disk();
+        disk.add_entry(1, "x ", 5).unwrap();
+        disk.add_entry(1, "x", 6).unwrap();
+
+        assert_eq!(disk.find_entry(1, "x "), Ok(Some(5)));
+        assert_eq!(disk.find_entry(1, "x"), Ok(Some(6)));
+        assert_eq!(disk.find_entry(1, " x"), Ok(None));
+        assert_eq!(disk.find_entry(1, "X"), Ok(None));
+
+        disk.remove_entry(1, "x ").unwrap();
+        assert_eq!(disk.find_entry(1, "x "), Ok(None));
+        assert_eq!(disk.find_entry(1, "x"), Ok(Some(6)));
+    }
+
+    #[test]
+    fn entries_are_found_in_their_hash_bucket() {
+        let mut disk = disk();
+        let directory = new_inode(&mut disk, 1, "carpeta", FileType::Directory);
+        for number in 0..disk.entries_per_block() * 2 {
+            disk.add_entry(directory, &format!("entrada{}", number), 1).unwrap();
+        }
+
+        let blocks = disk.directory_blocks(directory) as u64;
+        let per_block = disk.entries_per_block();
+        for number in 0..per_block * 2 {
+            let name = format!("entrada{}", number);
+            let (position, _) = disk.find_entry_position(directory, &name).unwrap().unwrap();
+            assert_eq!((position / per_block) as u64, hash_name(&name) % blocks);
+        }
+    }
+
+    #[test]
+    fn duplicate_names_are_rejected() {
+        let mut disk = disk();
+        let file = new_inode(&mut disk, 1, "archivo", FileType::RegularFile);
+        new_inode(&mut disk, 1, "carpeta", FileType::Directory);
+
+        assert_eq!(disk.add_entry(1, "archivo", 9), Err(QrfsError::AlreadyExists));
+        assert_eq!(disk.add_entry(1, "carpeta", 9).unwrap_err().errno(), EEXIST);
+        assert_eq!(disk.find_entry(1, "archivo"), Ok(Some(file)));
+        assert_eq!(disk.get_entries(1).unwrap().iter().flatten().filter(|entry| entry.name == "archivo").count(), 1);
+    }
+
+    #[test]
+    fn entries_need_a_directory() {
+        let mut disk = disk();
+        let file = new_inode(&mut disk, 1, "archivo", FileType::RegularFile);
+        disk.write_file(file, 0, b"contenido").unwrap();
+
+        assert_eq!(disk.add_entry(file, "a", 9), Err(QrfsError::NotDirectory));
+        assert_eq!(disk.grow_directory(file), Err(QrfsError::NotDirectory));
+        assert_eq!(disk.add_entry(999, "a", 9), Err(QrfsError::NotFound));
+        assert_eq!(disk.read_file(file, 0, 20), Ok(b"contenido".to_vec()));
+        assert_eq!(disk.get_inode(file).unwrap().attributes.size, 9);
+    }
+
+    #[test]
+    fn corrupt_blocks_are_reported_not_overwritten() {
+        let mut disk = disk();
+        let block = disk.get_file_block(1, 0).unwrap().unwrap();
+        disk.write_content_bytes(block, vec![0xff; 16]).unwrap();
+
+        assert_eq!(disk.find_entry(1, "a"), Err(QrfsError::Corrupted));
+        assert_eq!(disk.add_entry(1, "a", 9).unwrap_err().errno(), EIO);
+        assert_eq!(disk.get_content_bytes(block), &Some(vec![0xff; 16]));
+    }
+
+    #[test]
+    fn inode_exhaustion_maps_to_enospc() {
+        let mut disk = disk();
+        disk.max_files = disk.super_block.len();
+
+        while disk.inode_bitmap.free > 0 {
+            disk.allocate_ino().unwrap();
+        }
+
+        assert_eq!(disk.allocate_ino(), Err(QrfsError::NoSpace));
+        assert_eq!(QrfsError::NoSpace.errno(), ENOSPC);
+        assert_eq!(QrfsError::BufferTooSmall.errno(), ERANGE);
+    }
+
+    #[test]
+    fn config_log_level() {
+        let config = Config::from_args(args(&["/mnt", "--log-level", "DEBUG", "--log-file", "qrfs.log"])).unwrap();
+        assert_eq!(config.log_level, LogLevel::Debug);
+        assert_eq!(config.log_file, Some(PathBuf::from("qrfs.log")));
+
+        assert!(Config::from_args(args(&["/mnt", "--log-level", "fuerte"])).is_err());
+        assert!(Config::from_args(args(&["/mnt", "--log-level"])).is_err());
+        assert!(Config::from_args(args(&["/mnt", "--log-file"])).is_err());
+    }
+
+    #[test]
+    fn logger_levels() {
+        let logger = Logger { level: LogLevel::Warn, file: None };
+
+        assert!(logger.enabled(LogLevel::Error));
+        assert!(logger.enabled(LogLevel::Warn));
+        assert!(!logger.enabled(LogLevel::Info));
+        assert!(!logger.enabled(LogLevel::Off));
+        assert!(!Logger { level: LogLevel::Off, file: None }.enabled(LogLevel::Error));
+    }
+}